@@ -0,0 +1,82 @@
+use crate::sim::SimulationState;
+
+/// Undo/redo history of scene edits, kept as snapshots of the initial simulation state.
+pub struct History {
+    undo: Vec<SimulationState>,
+    redo: Vec<SimulationState>,
+    /// The scene as of the last recorded edit.
+    current: SimulationState,
+    /// Set while an edit gesture (dragging a value, typing into a field) is still going,
+    /// so that its changes fold into a single entry.
+    coalescing: bool
+}
+
+impl History {
+    const MAX_ENTRIES: usize = 200;
+
+    pub fn new(state: &SimulationState) -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+            current: state.clone(),
+            coalescing: false
+        }
+    }
+
+    /// Forgets all entries, e.g. after opening another scene.
+    pub fn reset(&mut self, state: &SimulationState) {
+        *self = Self::new(state);
+    }
+
+    /// Records `state` after an edit. With `continuing` set, further edits are merged into
+    /// this entry until [`History::end_gesture`] is called.
+    pub fn record(&mut self, state: &SimulationState, continuing: bool) {
+        if self.coalescing {
+            self.current = state.clone();
+        } else {
+            self.undo.push(std::mem::replace(&mut self.current, state.clone()));
+            self.redo.clear();
+
+            if self.undo.len() > Self::MAX_ENTRIES {
+                self.undo.remove(0);
+            }
+        }
+
+        self.coalescing = continuing;
+    }
+
+    pub fn end_gesture(&mut self) {
+        self.coalescing = false;
+    }
+
+    /// The scene as of the last recorded edit.
+    pub fn current(&self) -> &SimulationState {
+        &self.current
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Steps back one entry, returning the scene to restore.
+    pub fn undo(&mut self) -> Option<SimulationState> {
+        let previous = self.undo.pop()?;
+        self.redo.push(std::mem::replace(&mut self.current, previous));
+        self.coalescing = false;
+
+        Some(self.current.clone())
+    }
+
+    /// Steps forward one entry, returning the scene to restore.
+    pub fn redo(&mut self) -> Option<SimulationState> {
+        let next = self.redo.pop()?;
+        self.undo.push(std::mem::replace(&mut self.current, next));
+        self.coalescing = false;
+
+        Some(self.current.clone())
+    }
+}
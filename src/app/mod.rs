@@ -1,5 +1,16 @@
+mod history;
+mod preview;
+
 use winit::event::{Event, WindowEvent};
 
+#[cfg(not(target_arch = "wasm32"))]
+enum MenuAction {
+    Open,
+    Save,
+    SaveAs,
+    ExportSvg
+}
+
 #[allow(dead_code)]
 pub struct AppState<'a> {
     window_surface: wgpu::Surface<'a>,
@@ -18,19 +29,42 @@ pub struct AppState<'a> {
 
     sim_manager: Option<crate::sim::SimulationManager>,
 
-    sim_renderer: Box<dyn crate::sim::rendering::SimRenderer>,
+    sim_renderer: crate::sim::rendering::CpuSimRenderer,
     sim_render_state: crate::sim::SimulationState,
     sim_initial_state: crate::sim::SimulationState,
     sim_interface: crate::sim::SimulationInterface,
 
     playing: bool,
 
+    /// Paused at the playhead, editing the particles of that frame.
+    inspecting: bool,
+    preview_editor: preview::PreviewEditor,
+
+    /// Memory budget of the simulation frame cache, in MiB.
+    cache_budget_mib: usize,
+
     selected_trigger: String,
     new_trigger: Option<crate::sim::event::TriggerManager>,
 
     selected_constraint: String,
     new_constraint: Option<Box<dyn crate::sim::Constraint>>,
 
+    selected_link: String,
+    new_link: Option<Box<dyn crate::sim::LinkConstraint>>,
+
+    scene_path: Option<std::path::PathBuf>,
+    history: history::History,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    export_settings: crate::export::ExportSettings,
+    /// The pipe command came with an opened scene and hasn't been allowed to run yet.
+    #[cfg(not(target_arch = "wasm32"))]
+    export_command_unconfirmed: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_window_open: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_job: Option<crate::export::ExportJob>,
+
     window: &'a winit::window::Window
 }
 
@@ -120,14 +154,14 @@ impl<'a> AppState<'a> {
         let sim_manager = crate::sim::SimulationManager::new(sim_manager_tx, sim_interface_rx);
         let mut sim_interface = crate::sim::SimulationInterface::new(sim_interface_tx, sim_manager_rx);
 
-        let sim_renderer = Box::new(crate::sim::rendering::CpuSimRenderer::new());
+        let sim_renderer = crate::sim::rendering::CpuSimRenderer::new();
 
         let mut sim_initial_state = crate::sim::SimulationState::new();
         sim_initial_state.gravity_accel = glam::vec2(0.0, 0.25);
 
         sim_initial_state.add_trigger_manager(crate::sim::event::TriggerManager::new(
             Box::new(crate::sim::event::AnyLeftCircleTrigger::new(1.0)),
-            vec![Box::new(crate::sim::event::SpawnEvent { particle: crate::sim::Particle::new(glam::Vec2::ZERO, 0.05, egui::Color32::RED) })]
+            vec![Box::new(crate::sim::event::SpawnEvent::new(crate::sim::Particle::new(glam::Vec2::ZERO, 0.05, egui::Color32::RED)))]
         ));
 
         sim_initial_state.add_constraint(crate::sim::constraints::CircleConstraint::default());
@@ -154,14 +188,33 @@ impl<'a> AppState<'a> {
             sim_interface,
             sim_renderer,
             sim_render_state: sim_initial_state.clone(),
-            sim_initial_state,
 
             playing: false,
 
+            inspecting: false,
+            preview_editor: Default::default(),
+
+            cache_budget_mib: crate::sim::cache::FrameCache::DEFAULT_BUDGET / (1024 * 1024),
+
             selected_trigger: String::new(),
             new_trigger: None,
             selected_constraint: String::new(),
             new_constraint: None,
+            selected_link: String::new(),
+            new_link: None,
+
+            scene_path: None,
+            history: history::History::new(&sim_initial_state),
+            sim_initial_state,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            export_settings: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            export_command_unconfirmed: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_window_open: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_job: None,
 
             window
         })
@@ -186,6 +239,325 @@ impl<'a> AppState<'a> {
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reset_simulation(&mut self) {
+        self.sim_render_state = self.sim_initial_state.clone();
+        self.sim_interface.clear_frame_cache();
+        self.sim_interface.store_frame(0, self.sim_initial_state.clone());
+    }
+
+    /// Resimulates with the edited initial state from `time` onward, see [`crate::sim::SimulationState::first_change_time`].
+    fn resimulate_from(&mut self, time: f32) {
+        let frame = (time * crate::sim::FRAME_RATE).floor() as u32;
+
+        if frame == 0 {
+            self.sim_render_state = self.sim_initial_state.clone();
+            self.sim_interface.store_frame(0, self.sim_initial_state.clone());
+        } else {
+            self.sim_interface.invalidate_from(frame, self.sim_initial_state.clone());
+        }
+    }
+
+    /// Records an edit of the initial state in the history and resimulates what it affects.
+    /// With `editing` set, the edit is still in progress and later ones are merged into the same undo entry.
+    fn scene_edited(&mut self, editing: bool) {
+        // The first frame shows the camera on its target, the smoothing only applies once it moves
+        self.sim_initial_state.camera.snap(&self.sim_initial_state.particles);
+
+        if let Some(time) = self.sim_initial_state.first_change_time(self.history.current()) {
+            self.history.record(&self.sim_initial_state, editing);
+            self.resimulate_from(time);
+        }
+    }
+
+    /// Wheel or pinch zooms the preview around the pointer, dragging with the middle button
+    /// or on empty space pans it. This moves the scene camera's offset and zoom, which only change how
+    /// frames are viewed, so nothing is resimulated or recorded in the history.
+    fn navigate_preview(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        let rect = response.rect;
+        let viewport = self.sim_render_state.viewport(&self.sim_initial_state);
+
+        let mut zoom = 1.0;
+        if response.hovered() {
+            let (scroll, pinch) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            zoom = pinch * (scroll / 200.0).exp();
+        }
+
+        let mut pan = glam::Vec2::ZERO;
+        let pan_drag = response.dragged_by(egui::PointerButton::Middle)
+            || (response.dragged_by(egui::PointerButton::Primary) && !self.preview_editor.is_dragging() && !ui.input(|i| i.modifiers.shift));
+        if pan_drag {
+            let delta = response.drag_delta();
+            pan = -glam::vec2(viewport.logical_points_to_sim_units(delta.x, rect.width()), viewport.logical_points_to_sim_units(delta.y, rect.width()));
+        }
+
+        let camera = &mut self.sim_initial_state.camera;
+
+        if zoom != 1.0 && let Some(pos) = response.hover_pos() {
+            camera.zoom_at(zoom, viewport.screen_to_sim(pos, rect), &viewport);
+        }
+
+        camera.offset += pan;
+    }
+
+    /// Undo and redo leave the view where it is.
+    fn restore(&mut self, mut state: crate::sim::SimulationState) {
+        state.copy_view(&self.sim_initial_state);
+        let changed = state.first_change_time(&self.sim_initial_state);
+        self.sim_initial_state = state;
+
+        if let Some(time) = changed {
+            self.resimulate_from(time);
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(state) = self.history.undo() {
+            self.restore(state);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.history.redo() {
+            self.restore(state);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_scene(&mut self) {
+        let Some(path) = crate::util::pick_open_path("Open scene") else { return; };
+
+        match crate::sim::scene::load_scene_file(&path) {
+            Ok(scene) => {
+                self.sim_initial_state = scene.state;
+                if let Some(export) = scene.export {
+                    // Scene files can come from anyone, so a new command isn't run before it's been looked at
+                    self.export_command_unconfirmed |= export.pipe_command != self.export_settings.pipe_command;
+                    self.export_settings = export;
+                }
+                self.history.reset(&self.sim_initial_state);
+                self.scene_path = Some(path);
+                self.reset_simulation();
+            },
+            Err(e) => crate::util::show_error_dialog(&format!("Failed to open scene: \"{:?}\"", e))
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_scene(&mut self, save_as: bool) {
+        let path = match &self.scene_path {
+            Some(path) if !save_as => path.clone(),
+            _ => {
+                let Some(path) = crate::util::pick_save_path("Save scene") else { return; };
+                path
+            }
+        };
+
+        match crate::sim::scene::save_scene_file(&self.sim_initial_state, Some(&self.export_settings), &path) {
+            Ok(()) => self.scene_path = Some(path),
+            Err(e) => crate::util::show_error_dialog(&format!("Failed to save scene: \"{:?}\"", e))
+        }
+    }
+
+    /// Writes the frame under the playhead to an SVG file at the export resolution.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_svg_frame(&mut self) {
+        let Some(mut path) = crate::util::pick_save_path("Export frame as SVG") else { return; };
+
+        if path.extension().is_none() {
+            path.set_extension("svg");
+        }
+
+        if let Err(e) = crate::export::svg::write_svg(&path, &self.sim_render_state, &self.sim_initial_state, &self.export_settings) {
+            crate::util::show_error_dialog(&format!("Failed to export frame: \"{:?}\"", e));
+        }
+    }
+
+    /// Editor for the particle picked in the preview, at the frame under the playhead.
+    /// Edits are stored into the frame cache at that frame, so later frames branch off from it.
+    fn inspector_window(&mut self, ctx: &egui::Context, frame: u32, frame_ready: bool) {
+        let mut open = self.inspecting;
+
+        egui::Window::new(format!("Inspector (frame {})", frame))
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+            if frame == 0 {
+                ui.label("Frame 0 is the initial state. Edit it in the Particles panel.");
+                return;
+            }
+
+            if !frame_ready {
+                ui.label("Simulating...");
+                return;
+            }
+
+            let Some(index) = self.preview_editor.selected.and_then(|id| self.sim_render_state.particle_index(id)) else {
+                ui.label("Click a particle in the preview to select it.");
+                return;
+            };
+
+            use crate::sim::rendering::RenderableTool;
+
+            let mut id_salt = 0;
+            let (changed, remove) = self.sim_render_state.particles[index].draw(ui, &mut id_salt).inner;
+
+            if remove {
+                self.sim_render_state.particles.remove(index);
+                self.preview_editor.selected = None;
+            }
+
+            if changed || remove {
+                self.sim_interface.store_frame(frame, self.sim_render_state.clone());
+            }
+        });
+
+        self.inspecting &= open;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.export_window_open;
+
+        egui::Window::new("Export")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+            let running = self.export_job.as_mut().is_some_and(|j| j.is_running());
+            let settings = &mut self.export_settings;
+
+            ui.add_enabled_ui(!running, |ui| {
+                egui::Grid::new("export-settings").show(ui, |ui| {
+                    ui.label("Format");
+                    egui::ComboBox::new("export-format", "")
+                        .selected_text(settings.format.name())
+                        .show_ui(ui, |ui| {
+                        for format in crate::export::ExportFormat::ALL {
+                            if ui.selectable_value(&mut settings.format, format, format.name()).changed() {
+                                settings.fit_output_extension();
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Resolution");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut settings.width).range(1..=8192).suffix("px"));
+                        ui.label("x");
+                        ui.add(egui::DragValue::new(&mut settings.height).range(1..=8192).suffix("px"));
+                    });
+                    ui.end_row();
+
+                    ui.label("Background");
+                    ui.color_edit_button_srgba(&mut settings.background);
+                    ui.end_row();
+
+                    ui.label("Frame rate");
+                    ui.horizontal(|ui| {
+                        let mut fixed = settings.frame_rate.is_some();
+                        if ui.checkbox(&mut fixed, "Fixed").on_hover_text("Set the output frame rate instead of a frame step").changed() {
+                            settings.frame_rate = fixed.then(|| settings.output_frame_rate().round().max(1.0) as u32);
+                        }
+                        if let Some(fps) = &mut settings.frame_rate {
+                            ui.add(egui::DragValue::new(fps).range(1..=240).suffix(" fps"));
+                        }
+                    });
+                    ui.end_row();
+
+                    if settings.frame_rate.is_none() {
+                        ui.label("Frame step");
+                        ui.add(egui::DragValue::new(&mut settings.frame_step).range(1..=60).speed(0.1))
+                            .on_hover_text("Export every Nth simulation frame");
+                        ui.end_row();
+                    }
+
+                    if settings.format == crate::export::ExportFormat::Gif {
+                        ui.label("Palette quality");
+                        ui.add(egui::Slider::new(&mut settings.palette_quality, 1..=30));
+                        ui.end_row();
+                    }
+
+                    if matches!(settings.format, crate::export::ExportFormat::Pipe | crate::export::ExportFormat::Stream) {
+                        ui.label("Frame format");
+                        egui::ComboBox::new("export-pipe-format", "")
+                            .selected_text(settings.pipe_format.name())
+                            .show_ui(ui, |ui| {
+                            for format in crate::export::pipe::PipeFormat::ALL {
+                                if ui.selectable_value(&mut settings.pipe_format, format, format.name()).changed() {
+                                    settings.fit_output_extension();
+                                }
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    if settings.format == crate::export::ExportFormat::Pipe {
+                        ui.label("Command");
+                        ui.add(egui::TextEdit::multiline(&mut settings.pipe_command).desired_rows(2).code_editor())
+                            .on_hover_text("Placeholders: {width}, {height}, {fps}, {output}");
+                        ui.end_row();
+
+                        if self.export_command_unconfirmed {
+                            ui.label("");
+                            ui.vertical(|ui| {
+                                ui.colored_label(egui::Color32::YELLOW, "This command comes from the opened scene.\nCheck it before running it.");
+                                if ui.button("Allow command").clicked() {
+                                    self.export_command_unconfirmed = false;
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    }
+
+                    ui.label(if settings.format.is_sequence() { "Output folder" } else { "Output file" });
+                    let mut path = settings.output_path.display().to_string();
+                    if ui.text_edit_singleline(&mut path).changed() {
+                        settings.output_path = path.into();
+                    }
+                    ui.end_row();
+                });
+            });
+
+            let start_frame = (self.timeline_range.start() * crate::sim::FRAME_RATE).floor() as u32;
+            let end_frame = (self.timeline_range.end() * crate::sim::FRAME_RATE).floor() as u32;
+            ui.label(format!("Frames {} to {} ({:.2}s to {:.2}s) at {:.2} fps", start_frame, end_frame, self.timeline_range.start(), self.timeline_range.end(), self.export_settings.output_frame_rate()));
+
+            ui.separator();
+
+            if let Some(job) = &mut self.export_job {
+                match job.status().clone() {
+                    crate::export::ExportStatus::Progress { done, total } => {
+                        ui.horizontal(|ui| {
+                            let progress = if total > 0 { done as f32 / total as f32 } else { 0.0 };
+                            ui.add(egui::ProgressBar::new(progress).text(format!("{}/{}", done, total)).desired_width(200.0));
+
+                            if ui.button("Cancel").clicked() {
+                                job.cancel();
+                            }
+                        });
+                    },
+                    crate::export::ExportStatus::Finished => { ui.label("Export finished."); },
+                    crate::export::ExportStatus::Cancelled => { ui.label("Export cancelled."); },
+                    crate::export::ExportStatus::Failed(e) => { ui.colored_label(egui::Color32::RED, format!("Export failed: {}", e)); }
+                }
+            }
+
+            let blocked = self.export_settings.format == crate::export::ExportFormat::Pipe && self.export_command_unconfirmed;
+            if !running && ui.add_enabled(!blocked, egui::Button::new("Export")).clicked() {
+                let snapshot = self.sim_interface.snapshot_cache();
+
+                match crate::export::ExportJob::start(self.export_settings.clone(), snapshot, self.sim_initial_state.clone(), start_frame..=end_frame) {
+                    Ok(job) => self.export_job = Some(job),
+                    Err(e) => crate::util::show_error_dialog(&format!("Failed to start export: \"{:?}\"", e))
+                }
+            }
+        });
+
+        self.export_window_open = open;
+    }
+
     pub fn build_ui(&mut self, egui_input: egui::RawInput) -> egui::FullOutput {
         let preview_aspect = 9.0 / 16.0;
 
@@ -193,13 +565,99 @@ impl<'a> AppState<'a> {
 
         let mut sim_frame_idx = (self.timeline_pos * 60.0).floor() as u32;
 
-        if let Some(state) = self.sim_interface.try_get_frame(sim_frame_idx).cloned() {
-            self.sim_render_state = state;
-        }
+        let frame_ready = match self.sim_interface.try_get_frame(sim_frame_idx).cloned() {
+            Some(state) => {
+                self.sim_render_state = state;
+                true
+            },
+            None => false
+        };
 
         let frames_cached = self.sim_interface.get_cached();
+        let key_times = self.sim_initial_state.key_times();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut menu_action = None;
         
-        self.egui_state.egui_ctx().run(egui_input, |ctx| {
+        let egui_ctx = self.egui_state.egui_ctx().clone();
+        let output = egui_ctx.run(egui_input, |ctx| {
+            crate::sim::animation::set_editor_time(ctx, self.timeline_pos);
+
+            // Edits made while a value is being dragged or typed in are merged into one undo entry
+            let editing = ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input();
+            if !editing {
+                self.history.end_gesture();
+
+                let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+                let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+
+                if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+                    self.redo();
+                } else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+                    self.undo();
+                }
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            egui::TopBottomPanel::top("menu_panel").show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    ui.menu_button("File", |ui| {
+                        if ui.button("Open...").clicked() {
+                            ui.close_menu();
+                            menu_action = Some(MenuAction::Open);
+                        }
+
+                        if ui.button("Save").clicked() {
+                            ui.close_menu();
+                            menu_action = Some(MenuAction::Save);
+                        }
+
+                        if ui.button("Save As...").clicked() {
+                            ui.close_menu();
+                            menu_action = Some(MenuAction::SaveAs);
+                        }
+
+                        ui.separator();
+
+                        if ui.button("Export...").clicked() {
+                            ui.close_menu();
+                            self.export_window_open = true;
+                        }
+
+                        // Until the frame under the playhead arrives, the previous one would be exported instead
+                        let svg_button = ui.add_enabled(frame_ready, egui::Button::new("Export frame as SVG..."))
+                            .on_disabled_hover_text("Simulating...");
+                        if svg_button.clicked() {
+                            ui.close_menu();
+                            menu_action = Some(MenuAction::ExportSvg);
+                        }
+                    });
+
+                    ui.menu_button("Edit", |ui| {
+                        if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo").shortcut_text("Ctrl+Z")).clicked() {
+                            ui.close_menu();
+                            self.undo();
+                        }
+
+                        if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo").shortcut_text("Ctrl+Shift+Z")).clicked() {
+                            ui.close_menu();
+                            self.redo();
+                        }
+                    });
+
+                    if let Some(path) = &self.scene_path {
+                        ui.label(path.display().to_string());
+                    }
+                });
+            });
+
+            #[cfg(not(target_arch = "wasm32"))]
+            self.export_window(ctx);
+
+            if self.inspecting {
+                self.inspector_window(ctx, sim_frame_idx, frame_ready);
+            }
+
             egui::TopBottomPanel::bottom("timeline_panel")
                 .resizable(false)
                 .show(ctx, |ui| {
@@ -231,11 +689,32 @@ impl<'a> AppState<'a> {
                         .resizable(false)
                         .show_inside(ui, |ui| {
                         Self::play_pause_button(ui, &mut self.playing);
+
+                        if ui.toggle_value(&mut self.inspecting, "🔍 Inspect frame")
+                            .on_hover_text("Pause and edit the particles at the playhead. Everything after the frame is resimulated from the edit.")
+                            .clicked() {
+                            self.preview_editor.selected = None;
+                            self.playing &= !self.inspecting;
+                        }
+
+                        if self.inspecting && self.playing {
+                            self.inspecting = false;
+                        }
                         if ui.button("⟲ Clear simulation cache").clicked() {
                             self.sim_interface.clear_frame_cache();
                             self.sim_interface.store_frame(0, self.sim_initial_state.clone());
                             sim_frame_idx = 0;
                         }
+
+                        let (used, _) = self.sim_interface.cache_usage();
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Cache: {:.1} /", used as f32 / (1024.0 * 1024.0)));
+                            if ui.add(egui::DragValue::new(&mut self.cache_budget_mib).range(16..=65536).suffix(" MiB"))
+                                .on_hover_text("Memory budget of the simulation cache. Over budget, fewer frames are kept and the rest are recomputed when needed.")
+                                .changed() {
+                                self.sim_interface.set_cache_budget(self.cache_budget_mib * 1024 * 1024);
+                            }
+                        });
                     });
 
                 let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
@@ -256,12 +735,10 @@ impl<'a> AppState<'a> {
 
                 let painter = ui.painter();
 
-                if response.dragged() || response.clicked() {
-                    if let Some(pos) = response.interact_pointer_pos() {
-                        if rect.contains(pos) || rect.contains(ui.input(|i| i.pointer.press_origin().unwrap_or(egui::pos2(rect.left()-1.0, rect.top()-1.0)))) {
-                            self.timeline_pos = egui::remap_clamp(pos.x, slider_left..=slider_right, self.timeline_range.clone());
-                        }
-                    }
+                if (response.dragged() || response.clicked())
+                    && let Some(pos) = response.interact_pointer_pos()
+                    && (rect.contains(pos) || rect.contains(ui.input(|i| i.pointer.press_origin().unwrap_or(egui::pos2(rect.left()-1.0, rect.top()-1.0))))) {
+                    self.timeline_pos = egui::remap_clamp(pos.x, slider_left..=slider_right, self.timeline_range.clone());
                 }
 
                 let cached_pos = egui::remap_clamp(frames_cached as f32 / 60.0, self.timeline_range.clone(), slider_left..=slider_right);
@@ -287,6 +764,21 @@ impl<'a> AppState<'a> {
                     painter.line_segment([egui::pos2(tick_pos, slider_cy+h), egui::pos2(tick_pos, slider_cy-h)], egui::Stroke::new(1.0, egui::Color32::GRAY));
                 }
 
+                let key_size = tick_minor_height / 2.0;
+                for time in &key_times {
+                    if !self.timeline_range.contains(time) { continue; }
+
+                    let key_pos = egui::remap(*time, self.timeline_range.clone(), slider_left..=slider_right);
+                    let key_center = egui::pos2(key_pos, slider_cy - tick_major_height / 2.0 - key_size);
+
+                    painter.add(egui::Shape::convex_polygon(vec![
+                        key_center + egui::vec2(0.0, -key_size),
+                        key_center + egui::vec2(key_size, 0.0),
+                        key_center + egui::vec2(0.0, key_size),
+                        key_center + egui::vec2(-key_size, 0.0),
+                    ], egui::Color32::YELLOW, egui::Stroke::NONE));
+                }
+
                 self.timeline_pos = self.timeline_pos.clamp(*self.timeline_range.start(), *self.timeline_range.end());
 
                 let playhead_pos = egui::remap(self.timeline_pos, self.timeline_range.clone(), slider_left..=slider_right);
@@ -305,7 +797,26 @@ impl<'a> AppState<'a> {
                     .exact_width(preview_width)
                     .resizable(false)
                     .show_inside(ui, |ui| {
-                    self.sim_renderer.render(&self.sim_render_state, ui);
+                    self.sim_renderer.viewport = self.sim_render_state.viewport(&self.sim_initial_state);
+                    let response = self.sim_renderer.render(&self.sim_render_state, ui);
+
+                    // Frame 0 edits the scene itself, later frames are only editable in the inspector and branch off
+                    if !self.playing && frame_ready && (sim_frame_idx == 0 || self.inspecting) {
+                        let edit_scene = sim_frame_idx == 0;
+                        let viewport = &self.sim_renderer.viewport;
+                        let state = if edit_scene { &mut self.sim_initial_state } else { &mut self.sim_render_state };
+
+                        let changed = self.preview_editor.interact(ui, &response, viewport, state, edit_scene);
+                        self.preview_editor.paint(ui.painter(), viewport, response.rect, state, edit_scene);
+
+                        if changed && edit_scene {
+                            self.scene_edited(editing);
+                        } else if changed {
+                            self.sim_interface.store_frame(sim_frame_idx, self.sim_render_state.clone());
+                        }
+                    }
+
+                    self.navigate_preview(ui, &response);
                 });
 
                 egui::CentralPanel::default().show_inside(ui, |ui| {
@@ -320,19 +831,20 @@ impl<'a> AppState<'a> {
                         egui::ComboBox::new("trigger-selector", "")
                             .selected_text(self.selected_trigger.clone())
                             .show_ui(ui, |ui| {
-                            
-                            if ui.selectable_value(&mut self.selected_trigger, "Any particle left circular bound".into(), "Any particle left circular bound").clicked() {
-                                self.new_trigger = Some(crate::sim::event::TriggerManager::new(
-                                    Box::new(crate::sim::event::AnyLeftCircleTrigger::new(1.0)), vec![]
-                                ));
+                            for (name, create) in crate::sim::event::TRIGGER_KINDS {
+                                if ui.selectable_value(&mut self.selected_trigger, name.into(), name).clicked() {
+                                    let trigger = create();
+                                    let once = trigger.fires_on_most_steps();
+                                    let mut manager = crate::sim::event::TriggerManager::new(trigger, vec![]);
+                                    manager.once = once;
+                                    self.new_trigger = Some(manager);
+                                }
                             }
                         });
 
-                        if ui.button("+ Add").clicked() {
-                            if let Some(t) = &self.new_trigger {
-                                self.sim_initial_state.add_trigger_manager(t.clone());
-                                needs_update = true;
-                            }
+                        if ui.button("+ Add").clicked() && let Some(t) = &self.new_trigger {
+                            self.sim_initial_state.add_trigger_manager(t.clone());
+                            needs_update = true;
                         }
                     });
 
@@ -348,9 +860,7 @@ impl<'a> AppState<'a> {
                                 egui::Layout::left_to_right(egui::Align::Min),
                                 |ui| {
                             let mut remove = None;
-                            let mut i = 0usize;
-
-                            for manager in &mut self.sim_initial_state.trigger_managers {
+                            for (i, manager) in self.sim_initial_state.trigger_managers.iter_mut().enumerate() {
                                 let res = manager.draw(ui, &mut id_salt).inner;
 
                                 needs_update |= res.0;
@@ -358,7 +868,6 @@ impl<'a> AppState<'a> {
                                 if res.1 {
                                     remove = Some(i);
                                 }
-                                i += 1;
                             }
                             if let Some(r) = remove {
                                 needs_update = true;
@@ -387,11 +896,9 @@ impl<'a> AppState<'a> {
                             }
                         });
 
-                        if ui.button("+ Add").clicked() {
-                            if let Some(c) = &self.new_constraint {
-                                self.sim_initial_state.constraints.push(c.clone());
-                                needs_update = true;
-                            }
+                        if ui.button("+ Add").clicked() && let Some(c) = &self.new_constraint {
+                            self.sim_initial_state.add_boxed_constraint(c.clone());
+                            needs_update = true;
                         }
                     });
                     egui::ScrollArea::horizontal()
@@ -399,9 +906,8 @@ impl<'a> AppState<'a> {
                         .show(ui, |ui| {
                        
                         let mut remove = None;
-                        let mut i = 0usize;
-
-                        for constraint in &mut self.sim_initial_state.constraints {
+                        for (i, constraint) in self.sim_initial_state.constraints.iter_mut().enumerate() {
+                            ui.label(format!("Constraint {}", constraint.id()));
                             let res = constraint.draw(ui, &mut id_salt).inner;
 
                             needs_update |= res.0;
@@ -409,7 +915,6 @@ impl<'a> AppState<'a> {
                             if res.1 {
                                 remove = Some(i);
                             }
-                            i += 1;
                         }
 
                         if let Some(r) = remove {
@@ -420,6 +925,123 @@ impl<'a> AppState<'a> {
 
                     ui.separator();
 
+                    ui.horizontal(|ui| {
+                        ui.heading("Links");
+
+                        ui.separator();
+
+                        egui::ComboBox::new("link-selector", "")
+                            .selected_text(self.selected_link.clone())
+                            .show_ui(ui, |ui| {
+
+                            if ui.selectable_value(&mut self.selected_link, "Rod".into(), "Rod").clicked() {
+                                self.new_link = Some(Box::new(crate::sim::links::RodLink::default()));
+                            }
+
+                            if ui.selectable_value(&mut self.selected_link, "Rope".into(), "Rope").clicked() {
+                                self.new_link = Some(Box::new(crate::sim::links::RopeLink::default()));
+                            }
+
+                            if ui.selectable_value(&mut self.selected_link, "Spring".into(), "Spring").clicked() {
+                                self.new_link = Some(Box::new(crate::sim::links::SpringLink::default()));
+                            }
+                        });
+
+                        if ui.button("+ Add").clicked() && let Some(l) = &self.new_link {
+                            self.sim_initial_state.links.push(l.clone());
+                            needs_update = true;
+                        }
+
+                        if ui.button("+ Chain").on_hover_text("Add a pinned chain of particles").clicked() {
+                            let mut anchor = crate::sim::Particle::new(glam::vec2(0.0, -0.5), 0.02, egui::Color32::WHITE);
+                            anchor.mass = 0.0;
+                            let anchor = self.sim_initial_state.add_particle(anchor);
+
+                            let chain = self.sim_initial_state.add_chain(
+                                glam::vec2(0.05, -0.5), glam::vec2(0.45, -0.5), 7,
+                                crate::sim::Particle::new(glam::Vec2::ZERO, 0.02, egui::Color32::WHITE)
+                            );
+                            self.sim_initial_state.add_link(crate::sim::links::RopeLink::new(anchor, chain[0], 0.05));
+                            needs_update = true;
+                        }
+                    });
+                    egui::ScrollArea::horizontal()
+                        .id_salt("links-area")
+                        .show(ui, |ui| {
+
+                        let mut remove = None;
+
+                        for (i, link) in self.sim_initial_state.links.iter_mut().enumerate() {
+                            let res = link.draw(ui, &mut id_salt).inner;
+
+                            needs_update |= res.0;
+
+                            if res.1 {
+                                remove = Some(i);
+                            }
+                        }
+
+                        if let Some(r) = remove {
+                            self.sim_initial_state.links.remove(r);
+                            needs_update = true;
+                        }
+                    });
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.heading("Particles");
+
+                        ui.separator();
+
+                        if ui.button("+ Add").clicked() {
+                            self.sim_initial_state.add_particle(crate::sim::Particle::new(glam::Vec2::ZERO, 0.05, egui::Color32::WHITE));
+                            needs_update = true;
+                        }
+                    });
+                    egui::ScrollArea::horizontal()
+                        .id_salt("particles-area")
+                        .show(ui, |ui| {
+                        ui.allocate_ui_with_layout(
+                                egui::Vec2::new(f32::INFINITY, 0.0),
+                                egui::Layout::left_to_right(egui::Align::Min),
+                                |ui| {
+                            let mut remove = None;
+                            let mut duplicate = None;
+
+                            for (i, particle) in self.sim_initial_state.particles.iter_mut().enumerate() {
+                                ui.vertical(|ui| {
+                                    let res = particle.draw(ui, &mut id_salt).inner;
+
+                                    needs_update |= res.0;
+
+                                    if res.1 {
+                                        remove = Some(i);
+                                    }
+
+                                    if ui.button("Duplicate").clicked() {
+                                        duplicate = Some(i);
+                                    }
+                                });
+                            }
+
+                            if let Some(d) = duplicate {
+                                let mut copy = self.sim_initial_state.particles[d].clone();
+                                copy.position += glam::Vec2::splat(copy.radius * 2.0);
+                                copy.last_position = copy.position;
+                                self.sim_initial_state.add_particle(copy);
+                                needs_update = true;
+                            }
+
+                            if let Some(r) = remove {
+                                self.sim_initial_state.particles.remove(r);
+                                needs_update = true;
+                            }
+                        });
+                    });
+
+                    ui.separator();
+
                     ui.heading("Simulation Properties");
 
                     egui::Grid::new("sim-settings")
@@ -427,21 +1049,113 @@ impl<'a> AppState<'a> {
                         
                         ui.label("Gravity");
                         needs_update |= ui.add(egui::DragValue::new(&mut self.sim_initial_state.gravity_accel.x).speed(0.01).prefix("X:")).changed();
+                        needs_update |= crate::sim::animation::key_button(ui, &mut self.sim_initial_state.tracks, "gravity_x", self.sim_initial_state.gravity_accel.x);
                         needs_update |= ui.add(egui::DragValue::new(&mut self.sim_initial_state.gravity_accel.y).speed(0.01).prefix("Y:")).changed();
+                        needs_update |= crate::sim::animation::key_button(ui, &mut self.sim_initial_state.tracks, "gravity_y", self.sim_initial_state.gravity_accel.y);
+
+                        ui.end_row();
+
+                        ui.label("Substeps per frame");
+                        needs_update |= ui.add(egui::DragValue::new(&mut self.sim_initial_state.substeps).speed(0.1).range(1..=64)).changed();
+
+                        ui.end_row();
+
+                        ui.label("Constraint iterations");
+                        needs_update |= ui.add(egui::DragValue::new(&mut self.sim_initial_state.constraint_iterations).speed(0.1).range(1..=64)).changed();
 
                         ui.end_row();
 
                         needs_update |= ui.checkbox(&mut self.sim_initial_state.particle_collisions, "Particle-particle collisions").changed();
+
+                        ui.end_row();
+
+                        ui.label("Broadphase");
+                        egui::ComboBox::new("broadphase-selector", "")
+                            .selected_text(self.sim_initial_state.collision_broadphase.name())
+                            .show_ui(ui, |ui| {
+                            for broadphase in crate::sim::CollisionBroadphase::ALL {
+                                needs_update |= ui.selectable_value(&mut self.sim_initial_state.collision_broadphase, broadphase, broadphase.name()).changed();
+                            }
+                        });
+                    });
+
+                    ui.separator();
+
+                    ui.heading("Camera");
+
+                    egui::Grid::new("camera-settings")
+                        .show(ui, |ui| {
+                        let camera = &mut self.sim_initial_state.camera;
+                        let tracks = &mut self.sim_initial_state.tracks;
+
+                        ui.label("Follow");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::new("camera-follow-selector", "")
+                                .selected_text(camera.follow.name())
+                                .show_ui(ui, |ui| {
+                                let particle = match camera.follow {
+                                    crate::sim::rendering::CameraFollow::Particle(id) => id,
+                                    _ => self.preview_editor.selected.unwrap_or(0)
+                                };
+
+                                for follow in [crate::sim::rendering::CameraFollow::Fixed, crate::sim::rendering::CameraFollow::Particle(particle), crate::sim::rendering::CameraFollow::CenterOfMass] {
+                                    needs_update |= ui.selectable_value(&mut camera.follow, follow, follow.name()).changed();
+                                }
+                            });
+
+                            if let crate::sim::rendering::CameraFollow::Particle(id) = &mut camera.follow {
+                                needs_update |= ui.add(egui::DragValue::new(id).prefix("ID:")).changed();
+
+                                if let Some(selected) = self.preview_editor.selected
+                                    && ui.add_enabled(selected != *id, egui::Button::new("Use selected")).clicked() {
+                                    *id = selected;
+                                    needs_update = true;
+                                }
+                            }
+                        });
+
+                        ui.end_row();
+
+                        ui.label("Offset");
+                        ui.horizontal(|ui| {
+                            needs_update |= ui.add(egui::DragValue::new(&mut camera.offset.x).speed(0.01).prefix("X:")).changed();
+                            needs_update |= crate::sim::animation::key_button(ui, tracks, "camera_x", camera.offset.x);
+                            needs_update |= ui.add(egui::DragValue::new(&mut camera.offset.y).speed(0.01).prefix("Y:")).changed();
+                            needs_update |= crate::sim::animation::key_button(ui, tracks, "camera_y", camera.offset.y);
+                        });
+
+                        ui.end_row();
+
+                        ui.label("Zoom");
+                        ui.horizontal(|ui| {
+                            needs_update |= ui.add(egui::DragValue::new(&mut camera.zoom).speed(0.01).range(0.01..=100.0)).changed();
+                            needs_update |= crate::sim::animation::key_button(ui, tracks, "camera_zoom", camera.zoom);
+                        });
+
+                        ui.end_row();
+
+                        ui.label("Smoothing (s)");
+                        needs_update |= ui.add(egui::DragValue::new(&mut camera.smoothing).speed(0.01).range(0.0..=10.0)).changed();
                     });
                     
                     if needs_update {
-                        self.sim_render_state = self.sim_initial_state.clone();
-                        self.sim_interface.store_frame(0, self.sim_initial_state.clone());
+                        self.scene_edited(editing);
                     }
                     });
                 });
             });
-        })
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        match menu_action {
+            Some(MenuAction::Open) => self.open_scene(),
+            Some(MenuAction::Save) => self.save_scene(false),
+            Some(MenuAction::SaveAs) => self.save_scene(true),
+            Some(MenuAction::ExportSvg) => self.export_svg_frame(),
+            None => {}
+        }
+
+        output
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -460,11 +1174,11 @@ impl<'a> AppState<'a> {
         self.egui_state.egui_ctx().set_pixels_per_point(ppp);
         //self.egui_state.egui_ctx().set_debug_on_hover(true);
 
-        let egui_input = self.egui_state.take_egui_input(&self.window);
+        let egui_input = self.egui_state.take_egui_input(self.window);
 
         let egui_output = self.build_ui(egui_input);
 
-        self.egui_state.handle_platform_output(&self.window, egui_output.platform_output);
+        self.egui_state.handle_platform_output(self.window, egui_output.platform_output);
         let paint_jobs = self.egui_state.egui_ctx().tessellate(egui_output.shapes, ppp);
 
         let window_surface_texure = self.window_surface.get_current_texture()?;
@@ -560,7 +1274,7 @@ impl<'a> AppState<'a> {
                     ref event,
                     window_id
                 } if window_id == self.window.id() => {
-                    if self.egui_state.on_window_event(&self.window, event).consumed {
+                    if self.egui_state.on_window_event(self.window, event).consumed {
                         return;
                     }
 
@@ -0,0 +1,163 @@
+use crate::sim::{ParticleId, SimulationState, rendering::Viewport};
+
+/// Velocity arrows point to where the particle would be after this many seconds.
+const VELOCITY_ARROW_TIME: f32 = 0.25;
+/// Distance in points within which handles and small particles can be grabbed.
+const GRAB_RADIUS: f32 = 6.0;
+
+enum Drag {
+    Particle { id: ParticleId, offset: glam::Vec2 },
+    Velocity { id: ParticleId },
+    Handle { constraint: usize, index: usize }
+}
+
+/// Mouse editing of the state shown in the preview panel.
+///
+/// Click to place a particle or select one, drag a particle to move it, and shift- or right-drag from it
+/// (or drag the tip of the selected particle's arrow) to set its velocity. Constraints show handles to drag.
+#[derive(Default)]
+pub struct PreviewEditor {
+    pub selected: Option<ParticleId>,
+    drag: Option<Drag>
+}
+
+fn particle_mut(state: &mut SimulationState, id: ParticleId) -> Option<&mut crate::sim::Particle> {
+    state.particle_index(id).map(|i| &mut state.particles[i])
+}
+
+impl PreviewEditor {
+    /// Whether a particle, velocity or handle is being dragged, so the drag shouldn't pan the view.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Applies clicks and drags on the preview `response` to `state`, which is drawn with `viewport`.
+    /// Placing particles and constraint handles are only available with `edit_scene` set. Returns whether `state` changed.
+    pub fn interact(&mut self, ui: &egui::Ui, response: &egui::Response, viewport: &Viewport, state: &mut SimulationState, edit_scene: bool) -> bool {
+        let rect = response.rect;
+        let mut changed = false;
+
+        if response.drag_started() && let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+            let velocity_drag = ui.input(|i| i.modifiers.shift || i.pointer.secondary_down());
+            self.drag = self.grab(origin, velocity_drag, viewport, rect, state, edit_scene);
+        }
+
+        if response.dragged() && let Some(pos) = response.interact_pointer_pos() && let Some(drag) = &self.drag {
+            let point = viewport.screen_to_sim(pos, rect);
+
+            match *drag {
+                Drag::Particle { id, offset } => if let Some(particle) = particle_mut(state, id) {
+                    particle.position = point + offset;
+                    particle.last_position = particle.position;
+                    changed = true;
+                },
+                Drag::Velocity { id } => if let Some(particle) = particle_mut(state, id) {
+                    let velocity = (point - particle.position) / VELOCITY_ARROW_TIME;
+                    particle.set_velocity(velocity);
+                    changed = true;
+                },
+                Drag::Handle { constraint, index } => {
+                    let time = state.time;
+                    if let Some(constraint) = state.constraints.get_mut(constraint) {
+                        constraint.drag_handle(index, point, time);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if response.drag_stopped() {
+            self.drag = None;
+        }
+
+        if response.clicked() && let Some(pos) = response.interact_pointer_pos() {
+            let point = viewport.screen_to_sim(pos, rect);
+            self.selected = state.particle_at(point, viewport.logical_points_to_sim_units(GRAB_RADIUS, rect.width()));
+
+            if self.selected.is_none() && edit_scene {
+                self.selected = Some(self.place_particle(state, point));
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Adds a particle at `point`, copying the look of the selected one if there is one.
+    fn place_particle(&self, state: &mut SimulationState, point: glam::Vec2) -> ParticleId {
+        let template = self.selected.and_then(|id| state.particle_index(id)).map(|i| state.particles[i].clone());
+
+        let particle = match template {
+            Some(mut particle) => {
+                particle.position = point;
+                particle.last_position = point;
+                particle.with_velocity(glam::Vec2::ZERO)
+            },
+            None => crate::sim::Particle::new(point, 0.05, egui::Color32::WHITE)
+        };
+
+        state.add_particle(particle)
+    }
+
+    fn grab(&mut self, origin: egui::Pos2, velocity_drag: bool, viewport: &Viewport, rect: egui::Rect, state: &SimulationState, edit_scene: bool) -> Option<Drag> {
+        let near = |p: glam::Vec2| viewport.sim_to_screen(p, rect).distance(origin) <= GRAB_RADIUS;
+
+        if edit_scene {
+            for (constraint, c) in state.constraints.iter().enumerate() {
+                if let Some(index) = c.handles(state.time).into_iter().position(near) {
+                    return Some(Drag::Handle { constraint, index });
+                }
+            }
+        }
+
+        if let Some(particle) = self.selected.and_then(|id| state.particle_index(id)).map(|i| &state.particles[i])
+            && near(particle.position + particle.velocity() * VELOCITY_ARROW_TIME)
+            && particle.velocity() != glam::Vec2::ZERO {
+            return Some(Drag::Velocity { id: particle.id() });
+        }
+
+        let point = viewport.screen_to_sim(origin, rect);
+        let id = state.particle_at(point, viewport.logical_points_to_sim_units(GRAB_RADIUS, rect.width()))?;
+        let particle = &state.particles[state.particle_index(id)?];
+
+        self.selected = Some(id);
+
+        Some(if velocity_drag {
+            Drag::Velocity { id }
+        } else {
+            Drag::Particle { id, offset: particle.position - point }
+        })
+    }
+
+    /// Draws the selection, velocity arrow and handles over the preview.
+    pub fn paint(&self, painter: &egui::Painter, viewport: &Viewport, rect: egui::Rect, state: &SimulationState, edit_scene: bool) {
+        let to_screen = |p: glam::Vec2| viewport.sim_to_screen(p, rect);
+
+        if edit_scene {
+            for constraint in &state.constraints {
+                for handle in constraint.handles(state.time) {
+                    painter.circle(to_screen(handle), GRAB_RADIUS - 2.0, egui::Color32::DARK_GRAY, egui::Stroke::new(1.5, egui::Color32::WHITE));
+                }
+            }
+        }
+
+        if let Some(particle) = self.selected.and_then(|id| state.particle_index(id)).map(|i| &state.particles[i]) {
+            let center = to_screen(particle.position);
+            let radius = viewport.sim_units_to_logical_points(particle.radius, rect.width());
+            painter.circle_stroke(center, radius + 3.0, egui::Stroke::new(2.0, egui::Color32::YELLOW));
+
+            let tip = to_screen(particle.position + particle.velocity() * VELOCITY_ARROW_TIME);
+            if tip != center {
+                painter.arrow(center, tip - center, egui::Stroke::new(2.0, egui::Color32::LIGHT_BLUE));
+                painter.circle_filled(tip, GRAB_RADIUS - 2.0, egui::Color32::LIGHT_BLUE);
+            }
+        }
+
+        let help = if edit_scene {
+            "Click: add/select · Drag: move · Shift+drag: velocity"
+        } else {
+            "Click: select · Drag: move · Shift+drag: velocity"
+        };
+        painter.text(rect.left_top() + egui::vec2(6.0, 6.0), egui::Align2::LEFT_TOP, help, egui::FontId::proportional(12.0), egui::Color32::GRAY);
+    }
+}
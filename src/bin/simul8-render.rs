@@ -0,0 +1,206 @@
+//! Renders a saved scene without a window or GPU, for batch rendering from scripts.
+
+#[cfg(not(target_arch = "wasm32"))]
+use simul8::export::{ExportFormat, ExportSettings, pipe::PipeFormat};
+
+#[cfg(not(target_arch = "wasm32"))]
+const USAGE: &str = "\
+Usage: simul8-render --scene <file> --output <path> [options]
+
+Options:
+  --scene <file>        Scene saved from simul8
+  --output <path>       Output file, folder for png, or - for stdout with y4m and rgba
+  --format <format>     png, gif, apng, y4m, rgba or pipe (default: y4m)
+  --start <seconds>     Start of the rendered range (default: 0)
+  --end <seconds>       End of the rendered range (default: 10)
+  --width <pixels>      Frame width (default: 1080)
+  --height <pixels>     Frame height (default: 1920)
+  --fps <rate>          Output frame rate (default: 60, or 60 divided by the frame step
+                        saved with the scene if it has one and no frame rate)
+  --background <hex>    Background color as RRGGBB (default: 1B1B1B)
+  --command <command>   Command that frames are piped into with --format pipe, with the
+                        placeholders {width}, {height}, {fps} and {output}
+  --quiet               Don't print progress
+  --help                Show this message
+
+Resolution, frame rate and background default to the export settings saved with the
+scene, if it has any. Commands saved in scenes are never run, so --format pipe always
+needs --command.
+
+The simulation is deterministic and has no random seed, so a scene always renders the
+same frames.
+";
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Options {
+    scene: std::path::PathBuf,
+    output: std::path::PathBuf,
+    start: f32,
+    end: f32,
+    quiet: bool,
+    format: Option<(ExportFormat, PipeFormat)>,
+    width: Option<u32>,
+    height: Option<u32>,
+    fps: Option<u32>,
+    background: Option<egui::Color32>,
+    command: Option<String>
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Options {
+    /// The export settings saved with the scene, or the defaults, overridden by the given flags.
+    fn settings(&self, saved: Option<ExportSettings>) -> anyhow::Result<ExportSettings> {
+        let mut settings = saved.unwrap_or(ExportSettings {
+            frame_rate: Some(simul8::sim::FRAME_RATE as u32),
+            ..Default::default()
+        });
+
+        (settings.format, settings.pipe_format) = self.format.unwrap_or((ExportFormat::Stream, PipeFormat::Y4m));
+        settings.output_path = self.output.clone();
+        settings.width = self.width.unwrap_or(settings.width);
+        settings.height = self.height.unwrap_or(settings.height);
+        settings.frame_rate = self.fps.or(settings.frame_rate);
+        settings.background = self.background.unwrap_or(settings.background);
+
+        // Running a shell command from a scene file would let whoever wrote the scene run anything
+        settings.pipe_command = match &self.command {
+            Some(command) => command.clone(),
+            None if settings.format == ExportFormat::Pipe => anyhow::bail!("--format pipe needs --command"),
+            None => String::new()
+        };
+
+        if settings.width == 0 || settings.height == 0 {
+            anyhow::bail!("Resolution must be at least 1x1");
+        }
+        if settings.frame_rate == Some(0) {
+            anyhow::bail!("Frame rate must be at least 1");
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_color(hex: &str) -> anyhow::Result<egui::Color32> {
+    let hex = hex.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)
+        .ok_or_else(|| anyhow::anyhow!("Invalid color \"{}\", expected RRGGBB", hex))?;
+
+    Ok(egui::Color32::from_rgb((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Option<Options>> {
+    fn parse<T: std::str::FromStr>(flag: &str, value: String) -> anyhow::Result<T> {
+        value.parse().map_err(|_| anyhow::anyhow!("Invalid value \"{}\" for {}", value, flag))
+    }
+
+    let mut scene = None;
+    let mut output = None;
+    let mut options = Options {
+        scene: Default::default(),
+        output: Default::default(),
+        start: 0.0,
+        end: 10.0,
+        quiet: false,
+        format: None,
+        width: None,
+        height: None,
+        fps: None,
+        background: None,
+        command: None
+    };
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Ok(None);
+        }
+        if flag == "--quiet" {
+            options.quiet = true;
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for {}", flag))?;
+
+        match flag.as_str() {
+            "--scene" => scene = Some(value.into()),
+            "--output" => output = Some(value.into()),
+            "--format" => options.format = Some(match value.as_str() {
+                "png" => (ExportFormat::PngSequence, PipeFormat::Y4m),
+                "gif" => (ExportFormat::Gif, PipeFormat::Y4m),
+                "apng" => (ExportFormat::Apng, PipeFormat::Y4m),
+                "y4m" => (ExportFormat::Stream, PipeFormat::Y4m),
+                "rgba" => (ExportFormat::Stream, PipeFormat::RawRgba),
+                "pipe" => (ExportFormat::Pipe, PipeFormat::Y4m),
+                _ => anyhow::bail!("Unknown format \"{}\"", value)
+            }),
+            "--start" => options.start = parse(&flag, value)?,
+            "--end" => options.end = parse(&flag, value)?,
+            "--width" => options.width = Some(parse(&flag, value)?),
+            "--height" => options.height = Some(parse(&flag, value)?),
+            "--fps" => options.fps = Some(parse(&flag, value)?),
+            "--background" => options.background = Some(parse_color(&value)?),
+            "--command" => options.command = Some(value),
+            _ => anyhow::bail!("Unknown option {}", flag)
+        }
+    }
+
+    options.scene = scene.ok_or_else(|| anyhow::anyhow!("--scene is required"))?;
+    options.output = output.ok_or_else(|| anyhow::anyhow!("--output is required"))?;
+
+    if options.start < 0.0 || options.end < options.start {
+        anyhow::bail!("Invalid time range {} to {}", options.start, options.end);
+    }
+
+    Ok(Some(options))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn render(options: Options) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let scene = simul8::sim::scene::load_scene_file(&options.scene)?;
+    let settings = options.settings(scene.export)?;
+    let (start, end, quiet) = (options.start, options.end, options.quiet);
+    let initial = scene.state;
+
+    let first = (start * simul8::sim::FRAME_RATE).floor() as u32;
+    let last = (end * simul8::sim::FRAME_RATE).floor() as u32;
+    let frames = settings.frames(first..=last);
+
+    let sink = simul8::export::create_sink(&settings, frames.len() as u32)?;
+    let stepper = simul8::export::FrameStepper::new(vec![], initial.clone(), simul8::sim::FRAME_RATE);
+
+    simul8::export::render(&settings, &initial, stepper, frames, sink, |done, total| {
+        if !quiet {
+            eprint!("\rRendered {}/{} frames", done, total);
+            let _ = std::io::stderr().flush();
+        }
+        true
+    })?;
+
+    if !quiet {
+        eprintln!();
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let result = parse_args(std::env::args().skip(1)).and_then(|options| match options {
+        Some(options) => render(options),
+        None => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+    });
+
+    if let Err(e) = result {
+        eprintln!("simul8-render: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
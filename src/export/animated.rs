@@ -0,0 +1,95 @@
+use anyhow::Context;
+
+fn create_file(path: &std::path::Path) -> anyhow::Result<std::io::BufWriter<std::fs::File>> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create \"{}\"", parent.display()))?;
+    }
+
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create \"{}\"", path.display()))?;
+    Ok(std::io::BufWriter::new(file))
+}
+
+/// Looping GIF. GIF delays are whole centiseconds, so the frame rate is rounded.
+pub struct GifSink {
+    encoder: gif::Encoder<std::io::BufWriter<std::fs::File>>,
+    delay_cs: u16,
+    speed: i32
+}
+
+impl GifSink {
+    /// `palette_quality` goes from 1 (fastest) to 30 (best colors).
+    pub fn new(path: &std::path::Path, width: u32, height: u32, frame_duration: f32, palette_quality: u8) -> anyhow::Result<Self> {
+        let width = u16::try_from(width).context("GIF width must be at most 65535")?;
+        let height = u16::try_from(height).context("GIF height must be at most 65535")?;
+
+        let mut encoder = gif::Encoder::new(create_file(path)?, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(Self {
+            encoder,
+            delay_cs: (frame_duration * 100.0).round().max(1.0) as u16,
+            speed: 31 - palette_quality.clamp(1, 30) as i32
+        })
+    }
+}
+
+impl super::FrameSink for GifSink {
+    fn write_frame(&mut self, _index: u32, image: &egui::ColorImage) -> anyhow::Result<()> {
+        let mut rgba = super::sequence::rgba_bytes(image);
+
+        let mut frame = gif::Frame::from_rgba_speed(image.width() as u16, image.height() as u16, &mut rgba, self.speed);
+        frame.delay = self.delay_cs;
+
+        self.encoder.write_frame(&frame)?;
+        Ok(())
+    }
+}
+
+/// Looping animated PNG. Lossless, with an exact frame delay.
+/// The frame count is written up front, so an export that stops early deletes the file rather than leave it truncated.
+pub struct ApngSink {
+    path: std::path::PathBuf,
+    writer: Option<png::Writer<std::io::BufWriter<std::fs::File>>>
+}
+
+impl ApngSink {
+    pub fn new(path: &std::path::Path, width: u32, height: u32, frame_count: u32, frame_duration: f32) -> anyhow::Result<Self> {
+        let mut encoder = png::Encoder::new(create_file(path)?, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frame_count.max(1), 0)?;
+
+        // Delay as a fraction of a second, in milliseconds
+        encoder.set_frame_delay((frame_duration * 1000.0).round() as u16, 1000)?;
+
+        Ok(Self { path: path.to_path_buf(), writer: Some(encoder.write_header()?) })
+    }
+}
+
+impl super::FrameSink for ApngSink {
+    fn write_frame(&mut self, _index: u32, image: &egui::ColorImage) -> anyhow::Result<()> {
+        let writer = self.writer.as_mut().context("APNG is already finished")?;
+        writer.write_image_data(&super::sequence::rgba_bytes(image))?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        let Some(writer) = self.writer.take() else { return Ok(()); };
+
+        let result = writer.finish();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+        Ok(result?)
+    }
+}
+
+impl Drop for ApngSink {
+    fn drop(&mut self) {
+        // Not finished, so the export was cancelled or failed partway
+        if let Some(writer) = self.writer.take() {
+            drop(writer);
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
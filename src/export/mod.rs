@@ -0,0 +1,285 @@
+pub mod sequence;
+pub mod animated;
+pub mod pipe;
+pub mod svg;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::sim::SimulationState;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ExportFormat {
+    PngSequence,
+    Gif,
+    Apng,
+    Pipe,
+    Stream
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [ExportFormat::PngSequence, ExportFormat::Gif, ExportFormat::Apng, ExportFormat::Pipe, ExportFormat::Stream];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::PngSequence => "PNG sequence",
+            ExportFormat::Gif => "Animated GIF",
+            ExportFormat::Apng => "Animated PNG",
+            ExportFormat::Pipe => "Pipe to command",
+            ExportFormat::Stream => "Raw video stream"
+        }
+    }
+
+    /// Whether the output path names a directory rather than a single file.
+    pub fn is_sequence(&self) -> bool {
+        matches!(self, ExportFormat::PngSequence)
+    }
+
+    /// File extension of the output, or `None` for sequences. Pipes default to MP4, matching [`pipe::DEFAULT_COMMAND`].
+    pub fn extension(&self, pipe_format: pipe::PipeFormat) -> Option<&'static str> {
+        match self {
+            ExportFormat::PngSequence => None,
+            ExportFormat::Gif => Some("gif"),
+            ExportFormat::Apng => Some("png"),
+            ExportFormat::Pipe => Some("mp4"),
+            ExportFormat::Stream => Some(match pipe_format {
+                pipe::PipeFormat::RawRgba => "rgba",
+                pipe::PipeFormat::Y4m => "y4m"
+            })
+        }
+    }
+}
+
+/// Saved along with the scene, see [`crate::sim::scene::save_scene`].
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    pub width: u32,
+    pub height: u32,
+    pub background: egui::Color32,
+    /// Export every `frame_step`th simulation frame.
+    pub frame_step: u32,
+    /// Output frame rate, overriding `frame_step`. Each output frame shows the nearest simulation frame,
+    /// so rates that don't divide [`crate::sim::FRAME_RATE`] skip frames unevenly.
+    pub frame_rate: Option<u32>,
+    /// GIF palette quality, from 1 (fastest) to 30 (best colors).
+    pub palette_quality: u8,
+    /// Directory for image sequences, or the file for animations. Substituted for `{output}` in pipe commands.
+    pub output_path: std::path::PathBuf,
+    pub pipe_format: pipe::PipeFormat,
+    /// Shell command that frames are piped into. See [`pipe::expand_command`] for the placeholders.
+    pub pipe_command: String,
+}
+
+impl ExportSettings {
+    /// Frame rate of the exported video as a fraction, so Y4M can describe rates like 60/7 exactly.
+    pub fn output_frame_rate_fraction(&self) -> (u32, u32) {
+        match self.frame_rate {
+            Some(fps) => (fps.max(1), 1),
+            None => (crate::sim::FRAME_RATE as u32, self.frame_step.max(1))
+        }
+    }
+
+    /// Frame rate of the exported video after applying `frame_step` or `frame_rate`.
+    pub fn output_frame_rate(&self) -> f32 {
+        let (num, den) = self.output_frame_rate_fraction();
+        num as f32 / den as f32
+    }
+
+    /// The simulation frames to export from `frames`, at the output frame rate.
+    pub fn frames(&self, frames: std::ops::RangeInclusive<u32>) -> Vec<u32> {
+        let (start, end) = (*frames.start(), *frames.end());
+
+        match self.frame_rate {
+            Some(fps) => {
+                let step = crate::sim::FRAME_RATE as f64 / fps.max(1) as f64;
+                (0..).map(|i| start + (i as f64 * step).round() as u32).take_while(|&i| i <= end).collect()
+            },
+            None => frames.step_by(self.frame_step.max(1) as usize).collect()
+        }
+    }
+
+    /// Gives the output path the extension of the current format, so switching formats keeps the path usable.
+    /// Standard output (`-`) is left alone.
+    pub fn fit_output_extension(&mut self) {
+        if self.output_path.as_os_str() != "-" && self.output_path.file_name().is_some() {
+            self.output_path.set_extension(self.format.extension(self.pipe_format).unwrap_or(""));
+        }
+    }
+
+    pub fn expanded_pipe_command(&self) -> String {
+        pipe::expand_command(&self.pipe_command, self.width, self.height, self.output_frame_rate(), &self.output_path)
+    }
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::PngSequence,
+            width: 1080,
+            height: 1920,
+            background: egui::Color32::from_gray(27),
+            frame_step: 1,
+            frame_rate: None,
+            palette_quality: 20,
+            output_path: std::path::PathBuf::from("export"),
+            pipe_format: pipe::PipeFormat::Y4m,
+            pipe_command: pipe::DEFAULT_COMMAND.to_string()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ExportStatus {
+    Progress { done: u32, total: u32 },
+    Finished,
+    Cancelled,
+    Failed(String)
+}
+
+/// Destination for rendered frames, fed in order.
+pub trait FrameSink: Send {
+    fn write_frame(&mut self, index: u32, image: &egui::ColorImage) -> anyhow::Result<()>;
+    fn finish(self: Box<Self>) -> anyhow::Result<()> { Ok(()) }
+}
+
+/// Yields simulation frames in increasing order. Each frame is stepped offline with [`SimulationState::next_frame`],
+/// exactly as `SimulationManager` would, from the closest checkpoint of the manager's cache at or before it.
+pub struct FrameStepper {
+    checkpoints: Vec<(u32, SimulationState)>,
+    last: (u32, SimulationState),
+    fps: f32
+}
+
+impl FrameStepper {
+    /// `checkpoints` must be sorted by frame. Without a checkpoint at frame 0, `initial` is used as frame 0.
+    pub fn new(checkpoints: Vec<(u32, SimulationState)>, initial: SimulationState, fps: f32) -> Self {
+        Self { checkpoints, last: (0, initial), fps }
+    }
+
+    /// Panics if `index` is before the previously requested frame.
+    pub fn frame(&mut self, index: u32) -> &SimulationState {
+        assert!(index >= self.last.0, "frames must be requested in order");
+
+        let next = self.checkpoints.partition_point(|(i, _)| *i <= index);
+        if let Some((i, state)) = next.checked_sub(1).map(|n| &self.checkpoints[n]) && *i >= self.last.0 {
+            self.last = (*i, state.clone());
+        }
+
+        while self.last.0 < index {
+            self.last = (self.last.0 + 1, self.last.1.next_frame(self.fps));
+        }
+
+        &self.last.1
+    }
+}
+
+/// Opens the destination for `frame_count` frames in the format of `settings`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn create_sink(settings: &ExportSettings, frame_count: u32) -> anyhow::Result<Box<dyn FrameSink>> {
+    let (fps_num, fps_den) = settings.output_frame_rate_fraction();
+    let frame_duration = fps_den as f32 / fps_num as f32;
+
+    Ok(match settings.format {
+        ExportFormat::PngSequence => Box::new(sequence::PngSequenceSink::new(&settings.output_path)?),
+        ExportFormat::Gif => Box::new(animated::GifSink::new(&settings.output_path, settings.width, settings.height, frame_duration, settings.palette_quality)?),
+        ExportFormat::Apng => Box::new(animated::ApngSink::new(&settings.output_path, settings.width, settings.height, frame_count, frame_duration)?),
+        ExportFormat::Pipe => Box::new(pipe::PipeSink::new(
+            &settings.expanded_pipe_command(), settings.pipe_format,
+            settings.width, settings.height, fps_num, fps_den
+        )?),
+        ExportFormat::Stream => Box::new(pipe::StreamSink::new(
+            &settings.output_path, settings.pipe_format,
+            settings.width, settings.height, fps_num, fps_den
+        )?)
+    })
+}
+
+/// Renders `frames` into `sink` on the current thread, viewed with the camera settings of `scene`. `progress` is called
+/// with the frames done and the total after each one, and stops the export by returning false. Returns `Ok(false)` if stopped.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render(settings: &ExportSettings, scene: &SimulationState, mut stepper: FrameStepper, frames: Vec<u32>, mut sink: Box<dyn FrameSink>, mut progress: impl FnMut(u32, u32) -> bool) -> anyhow::Result<bool> {
+    let mut renderer = crate::sim::rendering::ImageSimRenderer::new(settings.width as usize, settings.height as usize, settings.background);
+
+    let total = frames.len() as u32;
+
+    for (done, index) in frames.into_iter().enumerate() {
+        let frame = stepper.frame(index);
+        renderer.viewport = frame.viewport(scene);
+        let image = renderer.render(frame, settings.background);
+        sink.write_frame(done as u32, image)?;
+
+        if !progress(done as u32 + 1, total) {
+            return Ok(false);
+        }
+    }
+
+    sink.finish()?;
+    Ok(true)
+}
+
+/// A running export. Poll [`ExportJob::status`] once per UI frame.
+pub struct ExportJob {
+    status_slot: crate::util::OverwriteSlot<ExportStatus>,
+    status: ExportStatus,
+    cancel: Arc<AtomicBool>
+}
+
+impl ExportJob {
+    /// Renders `frames` on a background thread. The frame cache snapshot is awaited on that thread,
+    /// and the export fails if the manager doesn't answer.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start(settings: ExportSettings, snapshot: flume::Receiver<Vec<(u32, SimulationState)>>, initial: SimulationState, frames: std::ops::RangeInclusive<u32>) -> anyhow::Result<Self> {
+        let frames = settings.frames(frames);
+        let sink = create_sink(&settings, frames.len() as u32)?;
+
+        let (status_slot, worker_slot) = crate::util::OverwriteSlot::new();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = Arc::clone(&cancel);
+
+        std::thread::spawn(move || {
+            let checkpoints = match snapshot.recv_timeout(std::time::Duration::from_secs(5)) {
+                Ok(checkpoints) => checkpoints,
+                Err(e) => {
+                    worker_slot.write(ExportStatus::Failed(format!("The simulation didn't send its frames: {}", e)));
+                    return;
+                }
+            };
+            let stepper = FrameStepper::new(checkpoints, initial.clone(), crate::sim::FRAME_RATE);
+
+            let progress = |done, total| {
+                worker_slot.write(ExportStatus::Progress { done, total });
+                !worker_cancel.load(Ordering::Relaxed)
+            };
+
+            let status = match render(&settings, &initial, stepper, frames, sink, progress) {
+                Ok(true) => ExportStatus::Finished,
+                Ok(false) => ExportStatus::Cancelled,
+                Err(e) => ExportStatus::Failed(format!("{:?}", e))
+            };
+            worker_slot.write(status);
+        });
+
+        Ok(Self {
+            status_slot,
+            status: ExportStatus::Progress { done: 0, total: 0 },
+            cancel
+        })
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn status(&mut self) -> &ExportStatus {
+        if let Some(status) = self.status_slot.try_read() {
+            self.status = status;
+        }
+        &self.status
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.status(), ExportStatus::Progress { .. })
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Context;
+use std::io::{Read, Write};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum PipeFormat {
+    RawRgba,
+    Y4m
+}
+
+impl PipeFormat {
+    pub const ALL: [PipeFormat; 2] = [PipeFormat::RawRgba, PipeFormat::Y4m];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PipeFormat::RawRgba => "Raw RGBA",
+            PipeFormat::Y4m => "Y4M"
+        }
+    }
+}
+
+pub const DEFAULT_COMMAND: &str = "ffmpeg -y -f yuv4mpegpipe -i - -c:v libx264 -pix_fmt yuv420p {output}";
+
+/// Fills in `{width}`, `{height}`, `{fps}` and `{output}` in a command template.
+/// The output path is quoted so the shell takes it literally.
+pub fn expand_command(template: &str, width: u32, height: u32, fps: f32, output: &std::path::Path) -> String {
+    template
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string())
+        .replace("{fps}", &format!("{}", fps))
+        .replace("{output}", &shell_quote(&output.display().to_string()))
+}
+
+/// Quotes `word` as a single shell argument.
+fn shell_quote(word: &str) -> String {
+    // Windows paths can't contain quotes
+    #[cfg(windows)]
+    return format!("\"{}\"", word);
+
+    // Inside single quotes nothing is special, so only the single quotes themselves need closing and escaping
+    #[cfg(not(windows))]
+    return format!("'{}'", word.replace('\'', "'\\''"));
+}
+
+/// Turns frames into the bytes of a raw RGBA or Y4M stream.
+struct FrameEncoder {
+    format: PipeFormat,
+    y4m_header: Option<String>,
+    buffer: Vec<u8>
+}
+
+impl FrameEncoder {
+    fn new(format: PipeFormat, width: u32, height: u32, fps_num: u32, fps_den: u32) -> Self {
+        let y4m_header = (format == PipeFormat::Y4m)
+            // The planes use the full 0-255 range, which readers otherwise assume is the limited TV range
+            .then(|| format!("YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444 XCOLORRANGE=FULL\n", width, height, fps_num, fps_den));
+
+        Self { format, y4m_header, buffer: vec![] }
+    }
+
+    /// The bytes for `image`, preceded by the stream header for the first frame.
+    fn encode(&mut self, image: &egui::ColorImage) -> &[u8] {
+        self.buffer.clear();
+
+        if let Some(header) = self.y4m_header.take() {
+            self.buffer.extend_from_slice(header.as_bytes());
+        }
+
+        match self.format {
+            PipeFormat::RawRgba => self.buffer.extend(image.pixels.iter().flat_map(|p| p.to_srgba_unmultiplied())),
+            PipeFormat::Y4m => {
+                self.buffer.extend_from_slice(b"FRAME\n");
+                write_y4m_planes(image, &mut self.buffer);
+            }
+        }
+
+        &self.buffer
+    }
+}
+
+/// Streams frames to the stdin of a shell command, e.g. an ffmpeg invocation.
+pub struct PipeSink {
+    child: std::process::Child,
+    stdin: Option<std::process::ChildStdin>,
+    stderr: Option<std::thread::JoinHandle<String>>,
+    encoder: FrameEncoder
+}
+
+impl PipeSink {
+    /// `fps` is given as a fraction so Y4M can describe rates like 60/7 exactly.
+    pub fn new(command: &str, format: PipeFormat, width: u32, height: u32, fps_num: u32, fps_den: u32) -> anyhow::Result<Self> {
+        #[cfg(windows)]
+        let mut process = { let mut p = std::process::Command::new("cmd"); p.arg("/C").arg(command); p };
+        #[cfg(not(windows))]
+        let mut process = { let mut p = std::process::Command::new("sh"); p.arg("-c").arg(command); p };
+
+        let mut child = process
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run \"{}\"", command))?;
+
+        let stdin = child.stdin.take();
+
+        // Drain stderr so a chatty encoder never blocks, keeping the tail for error messages
+        let stderr = child.stderr.take().map(|mut stderr| std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output);
+            let mut tail_start = output.len().saturating_sub(2048);
+            while !output.is_char_boundary(tail_start) {
+                tail_start += 1;
+            }
+            output[tail_start..].to_string()
+        }));
+
+        Ok(Self { child, stdin, stderr, encoder: FrameEncoder::new(format, width, height, fps_num, fps_den) })
+    }
+
+    fn fail(&mut self, error: anyhow::Error) -> anyhow::Error {
+        self.stdin = None;
+        let _ = self.child.wait();
+
+        match self.stderr.take().and_then(|s| s.join().ok()) {
+            Some(output) if !output.trim().is_empty() => error.context(format!("Encoder output:\n{}", output.trim())),
+            _ => error
+        }
+    }
+}
+
+/// Full-range BT.601 RGB to YCbCr, one plane after another.
+fn write_y4m_planes(image: &egui::ColorImage, buffer: &mut Vec<u8>) {
+    let rgb: Vec<[f32; 3]> = image.pixels.iter().map(|p| {
+        let [r, g, b, _] = p.to_srgba_unmultiplied();
+        [r as f32, g as f32, b as f32]
+    }).collect();
+
+    let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+
+    buffer.extend(rgb.iter().map(|[r, g, b]| to_u8(0.299 * r + 0.587 * g + 0.114 * b)));
+    buffer.extend(rgb.iter().map(|[r, g, b]| to_u8(128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b)));
+    buffer.extend(rgb.iter().map(|[r, g, b]| to_u8(128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b)));
+}
+
+impl super::FrameSink for PipeSink {
+    fn write_frame(&mut self, _index: u32, image: &egui::ColorImage) -> anyhow::Result<()> {
+        let bytes = self.encoder.encode(image);
+
+        let result = match &mut self.stdin {
+            Some(stdin) => stdin.write_all(bytes).context("Encoder stopped accepting frames"),
+            None => Err(anyhow::anyhow!("Encoder stdin is closed"))
+        };
+
+        result.map_err(|e| self.fail(e))
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.stdin = None;
+
+        let status = self.child.wait().context("Failed to wait for encoder")?;
+        let output = self.stderr.take().and_then(|s| s.join().ok()).unwrap_or_default();
+
+        if !status.success() {
+            anyhow::bail!("Encoder exited with {}\n{}", status, output.trim());
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PipeSink {
+    fn drop(&mut self) {
+        // Cancelled or failed exports shouldn't leave the encoder running
+        if self.stdin.take().is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Writes a raw RGBA or Y4M stream to a file, or to stdout if the path is `-`.
+pub struct StreamSink {
+    output: std::io::BufWriter<Box<dyn Write + Send>>,
+    encoder: FrameEncoder
+}
+
+impl StreamSink {
+    pub fn new(path: &std::path::Path, format: PipeFormat, width: u32, height: u32, fps_num: u32, fps_den: u32) -> anyhow::Result<Self> {
+        let output: Box<dyn Write + Send> = if path.as_os_str() == "-" {
+            Box::new(std::io::stdout())
+        } else {
+            Box::new(std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?)
+        };
+
+        Ok(Self {
+            output: std::io::BufWriter::new(output),
+            encoder: FrameEncoder::new(format, width, height, fps_num, fps_den)
+        })
+    }
+}
+
+impl super::FrameSink for StreamSink {
+    fn write_frame(&mut self, _index: u32, image: &egui::ColorImage) -> anyhow::Result<()> {
+        self.output.write_all(self.encoder.encode(image)).context("Failed to write frame")
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.output.flush().context("Failed to write frame")
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Context;
+
+/// Writes each frame to `frame_00000.png`, `frame_00001.png`, ... in a directory.
+pub struct PngSequenceSink {
+    directory: std::path::PathBuf
+}
+
+impl PngSequenceSink {
+    pub fn new(directory: &std::path::Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(directory).with_context(|| format!("Failed to create \"{}\"", directory.display()))?;
+
+        Ok(Self { directory: directory.to_path_buf() })
+    }
+}
+
+/// Straight-alpha RGBA8 bytes of an image, as image formats expect.
+pub fn rgba_bytes(image: &egui::ColorImage) -> Vec<u8> {
+    image.pixels.iter().flat_map(|p| p.to_srgba_unmultiplied()).collect()
+}
+
+pub fn write_png(path: &std::path::Path, image: &egui::ColorImage) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create \"{}\"", path.display()))?;
+
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgba_bytes(image))?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+impl super::FrameSink for PngSequenceSink {
+    fn write_frame(&mut self, index: u32, image: &egui::ColorImage) -> anyhow::Result<()> {
+        write_png(&self.directory.join(format!("frame_{:05}.png", index)), image)
+    }
+}
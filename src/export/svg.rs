@@ -0,0 +1,9 @@
+use anyhow::Context;
+
+/// Writes a single frame as an SVG document at the export resolution, viewed with the camera settings of `scene`.
+pub fn write_svg(path: &std::path::Path, state: &crate::sim::SimulationState, scene: &crate::sim::SimulationState, settings: &super::ExportSettings) -> anyhow::Result<()> {
+    let mut renderer = crate::sim::rendering::SvgSimRenderer::new(settings.width, settings.height, settings.background);
+    renderer.viewport = state.viewport(scene);
+
+    std::fs::write(path, renderer.render(state)).with_context(|| format!("Failed to write \"{}\"", path.display()))
+}
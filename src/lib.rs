@@ -1,6 +1,7 @@
 pub mod app;
 pub mod util;
 pub mod sim;
+pub mod export;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
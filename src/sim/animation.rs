@@ -0,0 +1,276 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Easing {
+    Linear,
+    Step,
+    EaseIn,
+    EaseOut,
+    EaseInOut
+}
+
+impl Easing {
+    pub const ALL: [Easing; 5] = [Easing::Linear, Easing::Step, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::Step => "Step",
+            Easing::EaseIn => "Ease in",
+            Easing::EaseOut => "Ease out",
+            Easing::EaseInOut => "Ease in/out"
+        }
+    }
+
+    /// Maps `t` in [0, 1] to the eased progress in [0, 1].
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::Step => if t < 1.0 { 0.0 } else { 1.0 },
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    /// Integral of [`Easing::apply`] from 0 to `t`.
+    pub fn integral(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => 0.5 * t * t,
+            Easing::Step => 0.0,
+            Easing::EaseIn => t * t * t / 3.0,
+            Easing::EaseOut => t * t - t * t * t / 3.0,
+            Easing::EaseInOut => t * t * t - 0.5 * t * t * t * t
+        }
+    }
+}
+
+/// A value at a point in simulation time. `easing` shapes the transition from this key to the next one.
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: f32,
+    pub easing: Easing
+}
+
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Track {
+    keyframes: Vec<Keyframe>
+}
+
+impl Track {
+    /// Keys closer together than this are considered to be at the same time.
+    pub const TIME_EPSILON: f32 = 1.0 / 120.0;
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn key_at(&self, time: f32) -> Option<&Keyframe> {
+        self.keyframes.iter().find(|k| (k.time - time).abs() < Self::TIME_EPSILON)
+    }
+
+    pub fn key_at_mut(&mut self, time: f32) -> Option<&mut Keyframe> {
+        self.keyframes.iter_mut().find(|k| (k.time - time).abs() < Self::TIME_EPSILON)
+    }
+
+    /// Inserts a key, replacing any key already at `time` but keeping its easing.
+    pub fn set_key(&mut self, time: f32, value: f32) {
+        if let Some(key) = self.key_at_mut(time) {
+            key.value = value;
+            return;
+        }
+
+        let index = self.keyframes.partition_point(|k| k.time < time);
+        self.keyframes.insert(index, Keyframe { time, value, easing: Easing::Linear });
+    }
+
+    pub fn remove_key(&mut self, time: f32) {
+        self.keyframes.retain(|k| (k.time - time).abs() >= Self::TIME_EPSILON);
+    }
+
+    /// Earliest time from which sampling `self` can give a different value than `other`, or `None` if they're equal.
+    /// That is the time of the key before the first one that differs, since the curve leading up to it changes too.
+    pub fn first_change_time(&self, other: &Track) -> Option<f32> {
+        let common = self.keyframes.iter().zip(&other.keyframes).take_while(|(a, b)| a == b).count();
+
+        if common == self.keyframes.len() && common == other.keyframes.len() {
+            return None;
+        }
+
+        Some(common.checked_sub(1).map_or(0.0, |k| self.keyframes[k].time))
+    }
+
+    /// Value of the track at `time`, holding the first and last keys outside their range.
+    pub fn sample(&self, time: f32) -> Option<f32> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+
+        let t = a.easing.apply((time - a.time) / (b.time - a.time));
+        Some(a.value + (b.value - a.value) * t)
+    }
+
+    /// Integral of the sampled value from 0 to `time`, e.g. the angle turned by a keyframed angular velocity.
+    pub fn integral(&self, time: f32) -> Option<f32> {
+        Some(self.antiderivative(time)? - self.antiderivative(0.0)?)
+    }
+
+    /// Integral of the sampled value from the first key to `time`, negative before it.
+    fn antiderivative(&self, time: f32) -> Option<f32> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if time <= first.time {
+            return Some(first.value * (time - first.time));
+        }
+
+        let mut total = 0.0;
+        for pair in self.keyframes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let duration = b.time - a.time;
+            if duration <= 0.0 {
+                continue;
+            }
+
+            let t = ((time - a.time) / duration).min(1.0);
+            total += duration * (a.value * t + (b.value - a.value) * a.easing.integral(t));
+
+            if time <= b.time {
+                return Some(total);
+            }
+        }
+
+        Some(total + last.value * (time - last.time))
+    }
+}
+
+/// Keyframe tracks for the named numeric parameters of one object.
+#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Tracks {
+    tracks: std::collections::BTreeMap<String, Track>
+}
+
+impl Tracks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, parameter: &str) -> Option<&Track> {
+        self.tracks.get(parameter)
+    }
+
+    pub fn get_mut(&mut self, parameter: &str) -> &mut Track {
+        self.tracks.entry(parameter.to_string()).or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Track)> {
+        self.tracks.iter().map(|(name, track)| (name.as_str(), track))
+    }
+
+    pub fn key_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.tracks.values().flat_map(|t| t.keyframes.iter().map(|k| k.time))
+    }
+
+    /// Earliest [`Track::first_change_time`] over all parameters, or `None` if every track is the same.
+    pub fn first_change_time(&self, other: &Tracks) -> Option<f32> {
+        let empty = Track::default();
+
+        self.tracks.keys().chain(other.tracks.keys())
+            .filter_map(|name| {
+                let a = self.tracks.get(name).unwrap_or(&empty);
+                let b = other.tracks.get(name).unwrap_or(&empty);
+                a.first_change_time(b)
+            })
+            .min_by(f32::total_cmp)
+    }
+
+    /// Writes the sampled value of each track into the parameter of the same name.
+    /// Parameters without a track keep their current value.
+    pub fn apply<const N: usize>(&self, time: f32, parameters: [(&str, &mut f32); N]) {
+        for (name, value) in parameters {
+            if let Some(v) = self.get(name).and_then(|t| t.sample(time)) {
+                *value = v;
+            }
+        }
+    }
+
+    pub fn cleanup(&mut self) {
+        self.tracks.retain(|_, t| !t.is_empty());
+    }
+}
+
+const EDITOR_TIME_ID: &str = "animation-editor-time";
+
+/// Sets the time at which keyframe buttons in editors insert keys. Called by the app once per frame.
+pub fn set_editor_time(ctx: &egui::Context, time: f32) {
+    ctx.data_mut(|d| d.insert_temp(egui::Id::new(EDITOR_TIME_ID), time));
+}
+
+pub fn editor_time(ctx: &egui::Context) -> f32 {
+    ctx.data(|d| d.get_temp(egui::Id::new(EDITOR_TIME_ID))).unwrap_or(0.0)
+}
+
+/// Diamond button that keys `parameter` to `value` at the editor time.
+/// Right-click to pick the key's easing or remove it. Returns whether the tracks changed.
+pub fn key_button(ui: &mut egui::Ui, tracks: &mut Tracks, parameter: &str, value: f32) -> bool {
+    let time = editor_time(ui.ctx());
+    let mut changed = false;
+
+    let has_key = tracks.get(parameter).and_then(|t| t.key_at(time)).is_some();
+    let animated = tracks.get(parameter).is_some_and(|t| !t.is_empty());
+
+    let color = if has_key { egui::Color32::YELLOW } else if animated { egui::Color32::LIGHT_YELLOW } else { egui::Color32::GRAY };
+    let response = ui.add(egui::Button::new(egui::RichText::new("◆").color(color)).frame(false))
+        .on_hover_text("Set key (right-click for options)");
+
+    if response.clicked() {
+        tracks.get_mut(parameter).set_key(time, value);
+        changed = true;
+    }
+
+    response.context_menu(|ui| {
+        let track = tracks.get_mut(parameter);
+
+        if let Some(key) = track.key_at_mut(time) {
+            for easing in Easing::ALL {
+                changed |= ui.selectable_value(&mut key.easing, easing, easing.name()).changed();
+            }
+
+            ui.separator();
+
+            if ui.button("Remove key").clicked() {
+                track.remove_key(time);
+                changed = true;
+                ui.close_menu();
+            }
+        } else {
+            ui.label("No key at this time");
+        }
+
+        if !track.is_empty() && ui.button("Clear track").clicked() {
+            *track = Track::default();
+            changed = true;
+            ui.close_menu();
+        }
+    });
+
+    if changed {
+        tracks.cleanup();
+    }
+
+    changed
+}
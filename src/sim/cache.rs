@@ -0,0 +1,183 @@
+use super::SimulationState;
+
+struct Checkpoint {
+    state: SimulationState,
+    size: usize,
+    /// Frames stored from outside (the initial state, edits) can't be recomputed, so they are never dropped.
+    pinned: bool
+}
+
+/// Sparse store of simulated frames. Every `interval`th frame is kept as a checkpoint and the frames in between
+/// are recomputed from the nearest earlier checkpoint on demand. When the estimated memory use goes over the
+/// budget, the interval is doubled and the checkpoints that are no longer on it are dropped.
+pub struct FrameCache {
+    checkpoints: std::collections::BTreeMap<u32, Checkpoint>,
+    checkpoint_bytes: usize,
+
+    /// Last simulated frame, from which the simulation continues.
+    frontier: Option<(u32, SimulationState)>,
+    /// Most recently recomputed frame, so playing through old frames doesn't restart from a checkpoint every time.
+    cursor: Option<(u32, SimulationState)>,
+
+    interval: u32,
+    budget: usize,
+    fps: f32
+}
+
+impl FrameCache {
+    pub const INITIAL_INTERVAL: u32 = 8;
+    pub const MAX_INTERVAL: u32 = 4096;
+    pub const DEFAULT_BUDGET: usize = 512 * 1024 * 1024;
+
+    pub fn new(fps: f32) -> Self {
+        Self {
+            checkpoints: std::collections::BTreeMap::new(),
+            checkpoint_bytes: 0,
+            frontier: None,
+            cursor: None,
+            interval: Self::INITIAL_INTERVAL,
+            budget: Self::DEFAULT_BUDGET,
+            fps
+        }
+    }
+
+    /// Number of frames simulated so far. Any frame below this can be returned without simulating further.
+    pub fn len(&self) -> u32 {
+        self.frontier.as_ref().map_or(0, |(i, _)| i + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frontier.is_none()
+    }
+
+    /// Frames between checkpoints.
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, bytes: usize) {
+        self.budget = bytes;
+        self.enforce_budget();
+    }
+
+    /// Estimated bytes held by the checkpoints, the frontier and the cursor.
+    pub fn memory_usage(&self) -> usize {
+        let size = |f: &Option<(u32, SimulationState)>| f.as_ref().map_or(0, |(_, s)| s.estimated_size());
+        self.checkpoint_bytes + size(&self.frontier) + size(&self.cursor)
+    }
+
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+        self.checkpoint_bytes = 0;
+        self.frontier = None;
+        self.cursor = None;
+        self.interval = Self::INITIAL_INTERVAL;
+    }
+
+    /// Simulates one more frame past the end of the cache.
+    pub fn step(&mut self) {
+        let (index, state) = match &self.frontier {
+            Some((i, state)) => (i + 1, state.next_frame(self.fps)),
+            None => (0, SimulationState::new().next_frame(self.fps))
+        };
+
+        if index % self.interval == 0 {
+            self.insert_checkpoint(index, state.clone(), false);
+        }
+
+        self.frontier = Some((index, state));
+    }
+
+    /// Returns frame `index`, simulating up to it or recomputing it from the nearest checkpoint as needed.
+    pub fn get(&mut self, index: u32) -> &SimulationState {
+        while self.len() <= index {
+            self.step();
+        }
+
+        if self.frontier.as_ref().is_some_and(|(i, _)| *i == index) {
+            return &self.frontier.as_ref().unwrap().1;
+        }
+
+        if self.checkpoints.contains_key(&index) {
+            return &self.checkpoints[&index].state;
+        }
+
+        let checkpoint_index = *self.checkpoints.range(..=index).next_back()
+            .expect("frame 0 is always a checkpoint").0;
+
+        let mut current = match self.cursor.take() {
+            Some(cursor) if cursor.0 >= checkpoint_index && cursor.0 <= index => cursor,
+            _ => (checkpoint_index, self.checkpoints[&checkpoint_index].state.clone())
+        };
+
+        while current.0 < index {
+            current = (current.0 + 1, current.1.next_frame(self.fps));
+        }
+
+        &self.cursor.insert(current).1
+    }
+
+    /// Replaces frame `index` with `state` and drops every later frame, so the simulation continues from it.
+    pub fn store(&mut self, index: u32, state: SimulationState) {
+        if index > 0 {
+            while self.len() < index {
+                self.step();
+            }
+        }
+
+        for (_, checkpoint) in self.checkpoints.split_off(&index) {
+            self.checkpoint_bytes -= checkpoint.size;
+        }
+
+        if self.cursor.as_ref().is_some_and(|(i, _)| *i >= index) {
+            self.cursor = None;
+        }
+
+        self.insert_checkpoint(index, state.clone(), true);
+        self.frontier = Some((index, state));
+    }
+
+    /// The checkpoints and the last simulated frame, in frame order. Any cached frame can be recomputed
+    /// by stepping forward from the closest of these at or before it.
+    pub fn snapshot(&self) -> Vec<(u32, SimulationState)> {
+        let mut frames: Vec<_> = self.checkpoints.iter().map(|(&i, c)| (i, c.state.clone())).collect();
+
+        if let Some((i, state)) = &self.frontier && !self.checkpoints.contains_key(i) {
+            frames.push((*i, state.clone()));
+        }
+
+        frames
+    }
+
+    fn insert_checkpoint(&mut self, index: u32, state: SimulationState, pinned: bool) {
+        let size = state.estimated_size();
+
+        if let Some(old) = self.checkpoints.insert(index, Checkpoint { state, size, pinned }) {
+            self.checkpoint_bytes -= old.size;
+        }
+        self.checkpoint_bytes += size;
+
+        self.enforce_budget();
+    }
+
+    fn enforce_budget(&mut self) {
+        while self.memory_usage() > self.budget && self.interval < Self::MAX_INTERVAL {
+            self.interval *= 2;
+
+            let interval = self.interval;
+            let mut freed = 0;
+            self.checkpoints.retain(|i, c| {
+                let keep = c.pinned || i % interval == 0;
+                if !keep {
+                    freed += c.size;
+                }
+                keep
+            });
+            self.checkpoint_bytes -= freed;
+        }
+    }
+}
@@ -1,66 +1,138 @@
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct CircleConstraint {
     radius: f32,
     elasticity: f32,
+    tracks: super::animation::Tracks,
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct HoleCircleConstraint {
     radius: f32,
     open_angle_start: f32,
     open_angle_end: f32,
-    //anim_timescale: f32,
+    angular_velocity: f32,
+    oscillation_amplitude: f32,
+    oscillation_frequency: f32,
     elasticity: f32,
+    friction: f32,
+    tracks: super::animation::Tracks,
 }
 
 impl CircleConstraint {
     pub fn new(radius: f32, elasticity: f32) -> Self {
-        Self { radius, elasticity }
+        Self { radius, elasticity, tracks: super::animation::Tracks::new() }
     }
-    pub fn default() -> Self {
-        Self { radius: 1.0, elasticity: 1.0 }
+}
+
+impl Default for CircleConstraint {
+    fn default() -> Self {
+        Self { radius: 1.0, elasticity: 1.0, tracks: super::animation::Tracks::new() }
     }
 }
 
 impl HoleCircleConstraint {
     const THICKNESS: f32 = 0.025;
-    pub fn new(radius: f32, open_angle_start: f32, open_angle_end: f32, /*anim_timescale: f32,*/ elasticity: f32) -> Self {
+    pub fn new(radius: f32, open_angle_start: f32, open_angle_end: f32, elasticity: f32) -> Self {
         Self {
-            radius, open_angle_start, open_angle_end, /*anim_timescale,*/ elasticity
+            radius, open_angle_start, open_angle_end, elasticity,
+            angular_velocity: 0.0,
+            oscillation_amplitude: 0.0,
+            oscillation_frequency: 0.0,
+            friction: 0.2,
+            tracks: super::animation::Tracks::new()
         }
     }
-    pub fn default() -> Self {
+
+    /// Spins the ring at `angular_velocity` radians per second.
+    pub fn with_rotation(mut self, angular_velocity: f32) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    /// Swings the ring back and forth by up to `amplitude` radians, `frequency` times per second, on top of any rotation.
+    pub fn with_oscillation(mut self, amplitude: f32, frequency: f32) -> Self {
+        self.oscillation_amplitude = amplitude;
+        self.oscillation_frequency = frequency;
+        self
+    }
+
+    /// How much of the ring's surface motion is passed on to balls it hits while it moves.
+    /// A ring that stands still doesn't slow balls down.
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    fn rotation_at(&self, time: f32) -> f32 {
+        use std::f32::consts::TAU;
+        // A keyframed speed is accumulated over time, so changing it doesn't make the ring jump
+        let spin = self.tracks.get("angular_velocity").and_then(|t| t.integral(time)).unwrap_or(self.angular_velocity * time);
+        spin + self.oscillation_amplitude * (TAU * self.oscillation_frequency * time).sin()
+    }
+
+    fn angular_velocity_at(&self, time: f32) -> f32 {
+        use std::f32::consts::TAU;
+        self.angular_velocity + self.oscillation_amplitude * TAU * self.oscillation_frequency * (TAU * self.oscillation_frequency * time).cos()
+    }
+}
+
+impl Default for HoleCircleConstraint {
+    fn default() -> Self {
         Self {
             radius: 1.0,
             open_angle_start: 0.2,
             open_angle_end: 0.4,
-            elasticity: 1.0
+            angular_velocity: 0.0,
+            oscillation_amplitude: 0.0,
+            oscillation_frequency: 0.0,
+            elasticity: 1.0,
+            friction: 0.2,
+            tracks: super::animation::Tracks::new()
         }
     }
 }
 
+#[typetag::serde]
 impl super::Constraint for CircleConstraint {
-    fn constrain(&self, particle: &mut super::Particle) {
+    fn constrain(&self, particle: &mut super::Particle, _time: f32) -> bool {
         let dist = particle.position.length() + particle.radius;
 
         let dist_over = (dist - self.radius).max(0.0);
 
-        particle.position -= particle.position * dist_over;
+        particle.position -= particle.position.normalize_or_zero() * dist_over;
 
         if dist > self.radius {
-            particle.velocity = particle.velocity.reflect(particle.position.normalize()) * self.elasticity;
-        
+            particle.bounce(-particle.position.normalize_or_zero(), self.elasticity);
         }
+
+        dist > self.radius
     }
 
-    fn draw_sim(&self, renderer: &dyn super::rendering::SimRenderer, ui: &mut egui::Ui, render_state: &super::rendering::RenderState) {
+    fn animate(&mut self, time: f32) {
+        self.tracks.apply(time, [("radius", &mut self.radius), ("elasticity", &mut self.elasticity)]);
+    }
+
+    fn tracks(&self) -> Option<&super::animation::Tracks> {
+        Some(&self.tracks)
+    }
+
+    fn draw_sim(&self, renderer: &mut dyn super::rendering::SimRenderer, _render_state: &super::rendering::RenderState) {
         const THICKNESS: f32 = 0.025;
-        renderer.circle(glam::Vec2::ZERO, self.radius, THICKNESS, egui::Color32::WHITE, ui, render_state);
+        renderer.circle(glam::Vec2::ZERO, self.radius, THICKNESS, egui::Color32::WHITE);
+    }
+
+    fn handles(&self, _time: f32) -> Vec<glam::Vec2> {
+        vec![glam::vec2(self.radius, 0.0)]
+    }
+
+    fn drag_handle(&mut self, _index: usize, to: glam::Vec2, _time: f32) {
+        self.radius = to.length();
     }
 }
 
+#[typetag::serde]
 impl super::Constraint for HoleCircleConstraint {
-    fn constrain(&self, particle: &mut super::Particle) {
+    fn constrain(&self, particle: &mut super::Particle, time: f32) -> bool {
         let pos_len_sq = particle.position.length_squared();
         let last_pos_len_sq = particle.last_position.length_squared();
         let radius_sq_inside = (self.radius - particle.radius - 0.5*Self::THICKNESS) * (self.radius - particle.radius - 0.5*Self::THICKNESS);
@@ -70,13 +142,14 @@ impl super::Constraint for HoleCircleConstraint {
         let hit_outside = pos_len_sq <= radius_sq_outside && last_pos_len_sq > radius_sq_outside;
 
         if !(hit_outside || hit_inside) {
-            return;
+            return false;
         }
 
         let pos_dir = particle.position.normalize();
 
-        let start_dir = glam::vec2(self.open_angle_start.cos(), self.open_angle_start.sin());
-        let end_dir = glam::vec2(self.open_angle_end.cos(), self.open_angle_end.sin());
+        let rotation = self.rotation_at(time);
+        let start_dir = glam::Vec2::from_angle(self.open_angle_start + rotation);
+        let end_dir = glam::Vec2::from_angle(self.open_angle_end + rotation);
 
         let in_open_arc = if self.open_angle_start < self.open_angle_end {
 
@@ -90,21 +163,40 @@ impl super::Constraint for HoleCircleConstraint {
         };
 
         if in_open_arc {
-            return;
+            return false;
         }
 
-        particle.velocity = particle.velocity.reflect(pos_dir) * self.elasticity;
-
         particle.position = pos_dir * if hit_inside { radius_sq_inside.sqrt() } else { radius_sq_outside.sqrt() };
+
+        let angular_velocity = self.angular_velocity_at(time);
+        let surface_velocity = particle.position.perp() * angular_velocity;
+        let friction = if angular_velocity != 0.0 { self.friction } else { 0.0 };
+        particle.bounce_moving(if hit_inside { -pos_dir } else { pos_dir }, surface_velocity, self.elasticity, friction);
+        true
+    }
+
+    fn animate(&mut self, time: f32) {
+        self.tracks.apply(time, [
+            ("radius", &mut self.radius),
+            ("open_angle_start", &mut self.open_angle_start),
+            ("open_angle_end", &mut self.open_angle_end),
+            ("angular_velocity", &mut self.angular_velocity),
+            ("elasticity", &mut self.elasticity)
+        ]);
     }
 
-    fn draw_sim(&self, renderer: &dyn super::rendering::SimRenderer, ui: &mut egui::Ui, render_state: &super::rendering::RenderState) {
+    fn tracks(&self) -> Option<&super::animation::Tracks> {
+        Some(&self.tracks)
+    }
+
+    fn draw_sim(&self, renderer: &mut dyn super::rendering::SimRenderer, render_state: &super::rendering::RenderState) {
         const SEGMENTS: u32 = 32;
 
         // Normalize angles to [0, TAU)
         use std::f32::consts::TAU;
-        let start_angle = self.open_angle_end.rem_euclid(TAU);
-        let mut end_angle = self.open_angle_start.rem_euclid(TAU);
+        let rotation = self.rotation_at(render_state.time());
+        let start_angle = (self.open_angle_end + rotation).rem_euclid(TAU);
+        let mut end_angle = (self.open_angle_start + rotation).rem_euclid(TAU);
 
         // Handle arc wrapping: if end < start, add TAU to end for smooth loop
         if end_angle < start_angle {
@@ -119,7 +211,25 @@ impl super::Constraint for HoleCircleConstraint {
             theta += step;
             let this_pos = glam::vec2((theta + 0.01).cos(), (theta + 0.01).sin()) * (self.radius);
 
-            renderer.line_segment(last_pos, this_pos, 0.025, egui::Color32::WHITE, ui, render_state);
+            renderer.line_segment(last_pos, this_pos, 0.025, egui::Color32::WHITE);
+        }
+    }
+
+    /// The two ends of the opening.
+    fn handles(&self, time: f32) -> Vec<glam::Vec2> {
+        let rotation = self.rotation_at(time);
+        vec![
+            glam::Vec2::from_angle(self.open_angle_start + rotation) * self.radius,
+            glam::Vec2::from_angle(self.open_angle_end + rotation) * self.radius
+        ]
+    }
+
+    fn drag_handle(&mut self, index: usize, to: glam::Vec2, time: f32) {
+        let angle = (to.to_angle() - self.rotation_at(time)).rem_euclid(std::f32::consts::TAU);
+
+        match index {
+            0 => self.open_angle_start = angle,
+            _ => self.open_angle_end = angle
         }
     }
 }
@@ -144,11 +254,13 @@ impl super::rendering::RenderableTool for CircleConstraint {
                 
                 ui.label("Radius:");
                 changed |= ui.add(egui::DragValue::new(&mut self.radius).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "radius", self.radius);
 
                 ui.end_row();
 
                 ui.label("Elasticity:");
                 changed |= ui.add(egui::DragValue::new(&mut self.elasticity).speed(0.01).range(0.0..=1.0)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "elasticity", self.elasticity);
             });
             *id_salt += 1;
             (changed, remove)
@@ -176,15 +288,36 @@ impl super::rendering::RenderableTool for HoleCircleConstraint {
                 
                 ui.label("Radius:");
                 changed |= ui.add(egui::DragValue::new(&mut self.radius).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "radius", self.radius);
 
                 ui.label("Open angle:");
                 changed |= ui.add(egui::DragValue::new(&mut self.open_angle_start).speed(0.01).range(0.0..=2.0*std::f32::consts::PI).prefix("Start:")).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "open_angle_start", self.open_angle_start);
                 changed |= ui.add(egui::DragValue::new(&mut self.open_angle_end).speed(0.01).range(0.0..=2.0*std::f32::consts::PI).prefix("End:")).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "open_angle_end", self.open_angle_end);
+
+                ui.end_row();
+
+                ui.label("Angular velocity:");
+                changed |= ui.add(egui::DragValue::new(&mut self.angular_velocity).speed(0.01).suffix(" rad/s")).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "angular_velocity", self.angular_velocity);
+
+                ui.end_row();
+
+                ui.label("Oscillation:");
+                changed |= ui.add(egui::DragValue::new(&mut self.oscillation_amplitude).speed(0.01).range(0.0..=std::f32::consts::PI).prefix("Amplitude:")).changed();
+                changed |= ui.add(egui::DragValue::new(&mut self.oscillation_frequency).speed(0.01).range(0.0..=f32::INFINITY).prefix("Frequency:").suffix(" Hz")).changed();
 
                 ui.end_row();
 
                 ui.label("Elasticity:");
                 changed |= ui.add(egui::DragValue::new(&mut self.elasticity).speed(0.01).range(0.0..=1.0)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "elasticity", self.elasticity);
+
+                ui.end_row();
+
+                ui.label("Friction:");
+                changed |= ui.add(egui::DragValue::new(&mut self.friction).speed(0.01).range(0.0..=f32::INFINITY)).changed();
             });
             *id_salt += 1;
             (changed, remove)
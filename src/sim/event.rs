@@ -1,42 +1,193 @@
 use super::rendering;
 
+#[typetag::serde(tag = "type")]
 pub trait SimEvent: Send + dyn_clone::DynClone + rendering::RenderableTool {
-    fn trigger(&self, sim: &mut super::SimulationState);
+    /// `causes` are the particles that made the trigger fire. Earlier events of the same trigger may have removed some of them.
+    fn trigger(&self, sim: &mut super::SimulationState, causes: &[TriggerCause]);
+
+    /// Updates keyframed parameters to their values at `time`.
+    fn animate(&mut self, _time: f32) {}
+    fn tracks(&self) -> Option<&super::animation::Tracks> { None }
+
+    /// Whether firing changes the scene definition itself (gravity, collision settings or constraints), which
+    /// [`super::SimulationState::apply_scene`] can't carry over to a later frame.
+    fn changes_scene(&self) -> bool { false }
 }
 dyn_clone::clone_trait_object!(SimEvent);
 
+#[typetag::serde(tag = "type")]
 pub trait SimTrigger: Send + dyn_clone::DynClone + rendering::RenderableTool {
-    fn is_triggered(&self, sim: &super::SimulationState) -> bool;
+    /// Checked once per substep, covering the time from `sim.time` up to `sim.time + dt`.
+    /// Returns the particles that made the trigger fire, or `None` if it didn't. Triggers that aren't caused by
+    /// particles, like timers, fire with no causes. Triggers can keep state between checks, which is stored
+    /// with each simulation frame.
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>>;
+
+    /// Whether the trigger fires on most substeps as created, so a new manager for it should only fire once
+    /// until it is set up.
+    fn fires_on_most_steps(&self) -> bool { false }
 }
 dyn_clone::clone_trait_object!(SimTrigger);
 
-#[derive(Clone)]
+/// A particle that made a trigger fire, and where it was at that moment.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TriggerCause {
+    pub particle: super::ParticleId,
+    pub position: glam::Vec2
+}
+
+/// The causes for the particles `ids`, each listed once in the order first seen. Returns `None` if there are none.
+fn collect_causes(sim: &super::SimulationState, ids: impl Iterator<Item = super::ParticleId>) -> Option<Vec<TriggerCause>> {
+    let mut causes: Vec<TriggerCause> = vec![];
+
+    for id in ids {
+        if causes.iter().any(|c| c.particle == id) {
+            continue;
+        }
+
+        if let Some(index) = sim.particle_index(id) {
+            causes.push(TriggerCause { particle: id, position: sim.particles[index].position });
+        }
+    }
+
+    (!causes.is_empty()).then_some(causes)
+}
+
+/// How often a trigger has fired so far in a simulation run.
+#[derive(Clone, Copy, Default, Debug)]
+struct FiringState {
+    count: u32,
+    last_time: Option<f32>
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct TriggerManager {
+    /// Tells managers apart across edits of the scene, assigned by [`super::SimulationState::add_trigger_manager`].
+    #[serde(default)]
+    id: u64,
     trigger: Box<dyn SimTrigger>,
     events: Vec<Box<dyn SimEvent>>,
 
+    /// Fire only the first time the trigger does.
+    #[serde(default)]
+    pub once: bool,
+    /// Minimum time in seconds between firings. Firings of the trigger during the cooldown are ignored.
+    #[serde(default)]
+    pub cooldown: f32,
+    #[serde(default)]
+    pub max_fires: Option<u32>,
+
+    #[serde(skip)]
+    firing: FiringState,
+
+    #[serde(skip)]
     selected_event: String,
+    #[serde(skip)]
     new_event: Option<Box<dyn SimEvent>>
 }
 
 impl TriggerManager {
     pub fn new(trigger: Box<dyn SimTrigger>, events: Vec<Box<dyn SimEvent>>) -> Self {
         Self {
+            id: 0,
             trigger, events,
+            once: false,
+            cooldown: 0.0,
+            max_fires: None,
+            firing: FiringState::default(),
             selected_event: String::new(),
             new_event: None
         }
     }
 
-    pub fn process(&self, sim: &mut super::SimulationState) {
-        if self.trigger.is_triggered(sim) {
-            for event in &self.events {
-                event.trigger(sim);
-            }
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(super) fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    /// Takes over how often `previous` has fired, for the same manager carried over to a later frame.
+    pub fn keep_firing_state(&mut self, previous: &TriggerManager) {
+        self.firing = previous.firing;
+    }
+
+    fn can_fire(&self, time: f32) -> bool {
+        let limit = if self.once { Some(1) } else { self.max_fires };
+
+        limit.is_none_or(|max| self.firing.count < max)
+            && self.firing.last_time.is_none_or(|last| time - last + super::animation::Track::TIME_EPSILON >= self.cooldown)
+    }
+
+    pub fn animate(&mut self, time: f32) {
+        for event in &mut self.events {
+            event.animate(time);
+        }
+    }
+
+    /// Whether any of the events changes the scene definition, see [`SimEvent::changes_scene`].
+    pub fn changes_scene(&self) -> bool {
+        self.events.iter().any(|e| e.changes_scene())
+    }
+
+    pub fn key_times(&self) -> Vec<f32> {
+        self.events.iter().filter_map(|e| e.tracks()).flat_map(|t| t.key_times()).collect()
+    }
+
+    /// Rough number of bytes this manager occupies. See [`super::SimulationState::estimated_size`].
+    pub fn estimated_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + std::mem::size_of_val(&*self.trigger)
+            + self.events.iter().map(|e| std::mem::size_of_val(&**e) + std::mem::size_of_val(e)).sum::<usize>()
+    }
+
+    pub fn process(&mut self, sim: &mut super::SimulationState, dt: f32) {
+        // Checked even when it can't fire, so stateful triggers see every step
+        let Some(causes) = self.trigger.check(sim, dt) else { return; };
+
+        if !self.can_fire(sim.time) {
+            return;
+        }
+
+        self.firing = FiringState { count: self.firing.count + 1, last_time: Some(sim.time) };
+
+        for event in &self.events {
+            event.trigger(sim, &causes);
         }
     }
 }
 
+type TriggerConstructor = fn() -> Box<dyn SimTrigger>;
+
+/// Triggers offered in the editor, with how to create a new one.
+pub const TRIGGER_KINDS: [(&str, TriggerConstructor); 9] = [
+    ("Any particle left circular bound", || Box::new(AnyLeftCircleTrigger::new(1.0))),
+    ("At time", || Box::new(AtTimeTrigger::new(1.0))),
+    ("Every N seconds", || Box::new(PeriodicTrigger::new(0.0, 0.5))),
+    ("Within time window", || Box::new(TimeWindowTrigger::new(0.0, 1.0))),
+    ("Particle hit constraint", || Box::new(ConstraintContactTrigger::new(None, None))),
+    ("Particles touched", || Box::new(ParticleContactTrigger::new(None, None))),
+    ("All of (AND)", || Box::new(AllTrigger::new(vec![]))),
+    ("Any of (OR)", || Box::new(AnyTrigger::new(vec![]))),
+    ("Not", || Box::new(NotTrigger::new(Box::new(AtTimeTrigger::new(1.0)))))
+];
+
+type EventConstructor = fn() -> Box<dyn SimEvent>;
+
+/// Events offered in the trigger editor, with how to create a new one.
+const EVENT_KINDS: [(&str, EventConstructor); 9] = [
+    ("Spawn Particle", || Box::new(SpawnEvent::new(super::Particle::new(glam::Vec2::ZERO, 0.05, egui::Color32::RED)))),
+    ("Remove Particles", || Box::new(RemoveEvent::new(ParticleTarget::Causes))),
+    ("Change Color", || Box::new(ColorEvent::new(ParticleTarget::Causes, vec![egui::Color32::RED], false))),
+    ("Scale Radius", || Box::new(ScaleRadiusEvent::new(ParticleTarget::Causes, 1.1))),
+    ("Change Velocity", || Box::new(VelocityEvent::new(ParticleTarget::Causes, VelocityChange::Impulse, glam::vec2(0.0, -0.5)))),
+    ("Set Gravity", || Box::new(SetGravityEvent::new(glam::vec2(0.0, 0.25)))),
+    ("Particle Collisions", || Box::new(ParticleCollisionsEvent::new(Switch::Toggle))),
+    ("Remove Constraint", || Box::new(RemoveConstraintEvent::new(0))),
+    ("Add Constraint", || Box::new(AddConstraintEvent::new(Box::new(super::constraints::CircleConstraint::default()))))
+];
+
 impl rendering::RenderableTool for TriggerManager {
     fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
         egui::Frame::group(ui.style())
@@ -55,6 +206,27 @@ impl rendering::RenderableTool for TriggerManager {
                     });
                 });
                 changed |= self.trigger.draw(ui, id_salt).inner.0;
+
+                ui.horizontal(|ui| {
+                    changed |= ui.checkbox(&mut self.once, "Fire once").changed();
+
+                    ui.add_enabled_ui(!self.once, |ui| {
+                        let mut limited = self.max_fires.is_some();
+                        if ui.checkbox(&mut limited, "At most").changed() {
+                            self.max_fires = limited.then_some(1);
+                            changed = true;
+                        }
+
+                        if let Some(max) = &mut self.max_fires {
+                            changed |= ui.add(egui::DragValue::new(max).speed(0.1).range(1..=u32::MAX).suffix(" times")).changed();
+                        }
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Cooldown");
+                    changed |= ui.add(egui::DragValue::new(&mut self.cooldown).speed(0.01).range(0.0..=f32::INFINITY).suffix("s")).changed();
+                });
            
                 ui.horizontal(|ui| {
                     ui.label("Events");
@@ -65,29 +237,25 @@ impl rendering::RenderableTool for TriggerManager {
                         .selected_text(self.selected_event.clone())
                         .show_ui(ui, |ui| {
                         *id_salt += 1;
-                        if ui.selectable_value(&mut self.selected_event, "Spawn Particle".into(), "Spawn Particle").clicked() {
-                            self.new_event = Some(Box::new(
-                                crate::sim::event::SpawnEvent { particle: crate::sim::Particle::new(glam::Vec2::ZERO, 0.05, egui::Color32::RED) }
-                            ));
+                        for (name, create) in EVENT_KINDS {
+                            if ui.selectable_value(&mut self.selected_event, name.into(), name).clicked() {
+                                self.new_event = Some(create());
+                            }
                         }
                     });
 
-                    if ui.button("+ Add").clicked() {
-                        if let Some(e) = &self.new_event {
-                            self.events.push(e.clone());
-                        }
+                    if ui.button("+ Add").clicked() && let Some(e) = &self.new_event {
+                        self.events.push(e.clone());
                     }
                 });
-                let mut i = 0usize;
                 let mut remove = None; 
-                for event in &mut self.events {
+                for (i, event) in self.events.iter_mut().enumerate() {
                     let res = event.draw(ui, id_salt).inner;
                     changed |= res.0;
 
                     if res.1 {
                         remove = Some(i);
                     }
-                    i += 1;
                 }
                 if let Some(r) = remove {
                     self.events.remove(r);
@@ -99,14 +267,49 @@ impl rendering::RenderableTool for TriggerManager {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct SpawnEvent {
     pub particle: super::Particle,
+    /// Spawn one particle at each cause of the trigger, offset by the particle's position, instead of one at that position.
+    #[serde(default)]
+    pub at_causes: bool,
+    tracks: super::animation::Tracks,
 }
 
+impl SpawnEvent {
+    pub fn new(particle: super::Particle) -> Self {
+        Self { particle, at_causes: false, tracks: super::animation::Tracks::new() }
+    }
+}
+
+#[typetag::serde]
 impl SimEvent for SpawnEvent {
-    fn trigger(&self, sim: &mut super::SimulationState) {
-        sim.add_particle(self.particle.clone());
+    fn trigger(&self, sim: &mut super::SimulationState, causes: &[TriggerCause]) {
+        if !self.at_causes {
+            sim.add_particle(self.particle.clone());
+            return;
+        }
+
+        for cause in causes {
+            let position = cause.position + self.particle.position;
+            sim.add_particle(super::Particle { position, last_position: position, ..self.particle.clone() });
+        }
+    }
+
+    fn animate(&mut self, time: f32) {
+        let particle = &mut self.particle;
+        self.tracks.apply(time, [
+            ("position_x", &mut particle.position.x),
+            ("position_y", &mut particle.position.y),
+            ("velocity_x", &mut particle.velocity.x),
+            ("velocity_y", &mut particle.velocity.y),
+            ("radius", &mut particle.radius)
+        ]);
+        particle.last_position = particle.position;
+    }
+
+    fn tracks(&self) -> Option<&super::animation::Tracks> {
+        Some(&self.tracks)
     }
 }
 
@@ -131,25 +334,47 @@ impl rendering::RenderableTool for SpawnEvent {
             egui::Grid::new(format!("particle-settings{}", id_salt))
                 .show(ui, |ui| {
 
-                let mut vx = (self.particle.position.x - self.particle.last_position.x) * 60.0;
-                let mut vy = (self.particle.position.y - self.particle.last_position.y) * 60.0;
+                let mut velocity = self.particle.velocity();
+
+                ui.label("Spawn at");
+                changed |= ui.checkbox(&mut self.at_causes, "Triggering particles")
+                    .on_hover_text("Spawn one particle at each particle that caused the trigger, offset by the position below")
+                    .changed();
+                ui.end_row();
 
-                ui.label("Position");
+                ui.label(if self.at_causes { "Offset" } else { "Position" });
                 changed |= ui.add(egui::DragValue::new(&mut self.particle.position.x).prefix("X:").speed(0.01)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "position_x", self.particle.position.x);
                 changed |= ui.add(egui::DragValue::new(&mut self.particle.position.y).prefix("Y:").speed(0.01)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "position_y", self.particle.position.y);
                 ui.end_row();
 
                 ui.label("Velocity");
 
-                changed |= ui.add(egui::DragValue::new(&mut vx).prefix("X:").speed(0.01)).changed();
-                changed |= ui.add(egui::DragValue::new(&mut vy).prefix("Y:").speed(0.01)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut velocity.x).prefix("X:").speed(0.01)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "velocity_x", velocity.x);
+                changed |= ui.add(egui::DragValue::new(&mut velocity.y).prefix("Y:").speed(0.01)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "velocity_y", velocity.y);
 
-                self.particle.last_position.x = self.particle.position.x - vx/60.0;
-                self.particle.last_position.y = self.particle.position.y - vy/60.0;
+                self.particle.last_position = self.particle.position;
+                self.particle.set_velocity(velocity);
                 ui.end_row();
 
                 ui.label("Radius");
                 changed |= ui.add(egui::DragValue::new(&mut self.particle.radius).speed(0.01)).changed();
+                changed |= super::animation::key_button(ui, &mut self.tracks, "radius", self.particle.radius);
+                ui.end_row();
+
+                ui.label("Mass");
+                changed |= ui.add(egui::DragValue::new(&mut self.particle.mass).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+                ui.end_row();
+
+                ui.label("Restitution");
+                changed |= ui.add(egui::DragValue::new(&mut self.particle.restitution).speed(0.01).range(0.0..=1.0)).changed();
+                ui.end_row();
+
+                ui.label("Friction");
+                changed |= ui.add(egui::DragValue::new(&mut self.particle.friction).speed(0.01).range(0.0..=f32::INFINITY)).changed();
                 ui.end_row();
                 
                 ui.label("Color");
@@ -167,7 +392,7 @@ impl rendering::RenderableTool for SpawnEvent {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct AnyLeftCircleTrigger {
     radius: f32
 }
@@ -178,15 +403,15 @@ impl AnyLeftCircleTrigger {
     }
 }
 
+#[typetag::serde]
 impl SimTrigger for AnyLeftCircleTrigger {
-    fn is_triggered(&self, sim: &super::SimulationState) -> bool {
-        for particle in &sim.particles {
-            if particle.position.length() > self.radius && particle.last_position.length() <= self.radius {
-                return true;
-            }
-        }
+    fn check(&mut self, sim: &super::SimulationState, _dt: f32) -> Option<Vec<TriggerCause>> {
+        let causes: Vec<TriggerCause> = sim.particles.iter()
+            .filter(|p| p.position.length() > self.radius && p.last_position.length() <= self.radius)
+            .map(|p| TriggerCause { particle: p.id(), position: p.position })
+            .collect();
 
-        false
+        (!causes.is_empty()).then_some(causes)
     }
 }
 
@@ -212,3 +437,986 @@ impl rendering::RenderableTool for AnyLeftCircleTrigger {
 
     }
 }
+
+/// Number of times in `start, start + interval, ...` that lie before `time`. Triggers compare this at both ends
+/// of a step, so each time falls into exactly one step even when it's on a step boundary.
+fn occurrences_before(time: f32, start: f32, interval: Option<f32>) -> u64 {
+    if time <= start {
+        return 0;
+    }
+
+    match interval {
+        Some(interval) if interval > 0.0 => ((time - start) / interval).ceil() as u64,
+        _ => 1
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct AtTimeTrigger {
+    time: f32
+}
+
+impl AtTimeTrigger {
+    pub fn new(time: f32) -> Self {
+        Self { time }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for AtTimeTrigger {
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>> {
+        (occurrences_before(sim.time + dt, self.time, None) > occurrences_before(sim.time, self.time, None)).then(Vec::new)
+    }
+}
+
+impl rendering::RenderableTool for AtTimeTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("At time");
+
+            egui::Grid::new(format!("at-time-settings{}", id_salt))
+                .show(ui, |ui| {
+                ui.label("Time:");
+                changed |= ui.add(egui::DragValue::new(&mut self.time).speed(0.01).range(0.0..=f32::INFINITY).suffix("s")).changed();
+            });
+            *id_salt += 1;
+            (changed, false)
+        })
+    }
+}
+
+/// Fires at `start` and every `interval` seconds after it.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct PeriodicTrigger {
+    start: f32,
+    interval: f32
+}
+
+impl PeriodicTrigger {
+    pub const MIN_INTERVAL: f32 = 0.001;
+
+    pub fn new(start: f32, interval: f32) -> Self {
+        Self { start, interval: interval.max(Self::MIN_INTERVAL) }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for PeriodicTrigger {
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>> {
+        let interval = Some(self.interval.max(Self::MIN_INTERVAL));
+        (occurrences_before(sim.time + dt, self.start, interval) > occurrences_before(sim.time, self.start, interval)).then(Vec::new)
+    }
+}
+
+impl rendering::RenderableTool for PeriodicTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("Every N seconds");
+
+            egui::Grid::new(format!("periodic-settings{}", id_salt))
+                .show(ui, |ui| {
+                ui.label("Every:");
+                changed |= ui.add(egui::DragValue::new(&mut self.interval).speed(0.01).range(Self::MIN_INTERVAL..=f32::INFINITY).suffix("s")).changed();
+                ui.end_row();
+
+                ui.label("Starting at:");
+                changed |= ui.add(egui::DragValue::new(&mut self.start).speed(0.01).range(0.0..=f32::INFINITY).suffix("s")).changed();
+            });
+            *id_salt += 1;
+            (changed, false)
+        })
+    }
+}
+
+/// Fires [`super::FRAME_RATE`] times a second during the time window from `start` to `end`, at `start` and every
+/// frame interval after it, so how often its events run doesn't depend on the number of substeps.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct TimeWindowTrigger {
+    start: f32,
+    end: f32
+}
+
+impl TimeWindowTrigger {
+    pub fn new(start: f32, end: f32) -> Self {
+        Self { start, end }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for TimeWindowTrigger {
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>> {
+        let interval = Some(1.0 / super::FRAME_RATE);
+        let before = occurrences_before(sim.time, self.start, interval);
+
+        // The first tick in this step has to be inside the window
+        let first_tick = self.start + before as f32 / super::FRAME_RATE;
+        (occurrences_before(sim.time + dt, self.start, interval) > before && first_tick <= self.end).then(Vec::new)
+    }
+}
+
+impl rendering::RenderableTool for TimeWindowTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("Within time window")
+                .on_hover_text(format!("Fires once per frame ({} times a second) inside the window, however many substeps there are", super::FRAME_RATE));
+
+            egui::Grid::new(format!("time-window-settings{}", id_salt))
+                .show(ui, |ui| {
+                ui.label("From:");
+                changed |= ui.add(egui::DragValue::new(&mut self.start).speed(0.01).range(0.0..=self.end).suffix("s")).changed();
+                ui.end_row();
+
+                ui.label("To:");
+                changed |= ui.add(egui::DragValue::new(&mut self.end).speed(0.01).range(self.start..=f32::INFINITY).suffix("s")).changed();
+            });
+            *id_salt += 1;
+            (changed, false)
+        })
+    }
+}
+
+/// Checkbox to enable a filter next to the value it filters on. Disabled filters match anything.
+fn filter_edit<T: egui::emath::Numeric + Default>(ui: &mut egui::Ui, filter: &mut Option<T>, any_text: &str) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        let mut enabled = filter.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *filter = enabled.then(T::default);
+            changed = true;
+        }
+
+        match filter {
+            Some(value) => changed |= ui.add(egui::DragValue::new(value).speed(0.1)).changed(),
+            None => { ui.label(any_text); }
+        }
+    });
+
+    changed
+}
+
+/// Fires when a particle hits a constraint, optionally only a specific constraint or particle.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConstraintContactTrigger {
+    /// See [`super::SceneConstraint::id`].
+    constraint: Option<super::ConstraintId>,
+    particle: Option<super::ParticleId>,
+    /// Keep firing while the particle rests against the constraint, not just when it hits.
+    #[serde(default)]
+    while_touching: bool
+}
+
+impl ConstraintContactTrigger {
+    pub fn new(constraint: Option<super::ConstraintId>, particle: Option<super::ParticleId>) -> Self {
+        Self { constraint, particle, while_touching: false }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for ConstraintContactTrigger {
+    fn check(&mut self, sim: &super::SimulationState, _dt: f32) -> Option<Vec<TriggerCause>> {
+        let particles = sim.contacts.iter()
+            .filter(|contact| self.while_touching || sim.is_new_contact(contact))
+            .filter_map(|contact| match *contact {
+                super::Contact::Constraint { constraint, particle }
+                    if self.constraint.is_none_or(|c| c == constraint) && self.particle.is_none_or(|p| p == particle) => Some(particle),
+                _ => None
+            });
+
+        collect_causes(sim, particles)
+    }
+}
+
+impl rendering::RenderableTool for ConstraintContactTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("Particle hit constraint");
+
+            egui::Grid::new(format!("constraint-contact-settings{}", id_salt))
+                .show(ui, |ui| {
+                ui.label("Constraint ID:");
+                changed |= filter_edit(ui, &mut self.constraint, "Any");
+                ui.end_row();
+
+                ui.label("Particle ID:");
+                changed |= filter_edit(ui, &mut self.particle, "Any");
+                ui.end_row();
+
+                changed |= ui.checkbox(&mut self.while_touching, "Keep firing while touching").changed();
+            });
+            *id_salt += 1;
+            (changed, false)
+        })
+    }
+}
+
+/// Fires when two particles touch, optionally only when specific ones are involved.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ParticleContactTrigger {
+    particle: Option<super::ParticleId>,
+    other: Option<super::ParticleId>,
+    /// Keep firing while the particles rest against each other, not just when they collide.
+    #[serde(default)]
+    while_touching: bool
+}
+
+impl ParticleContactTrigger {
+    pub fn new(particle: Option<super::ParticleId>, other: Option<super::ParticleId>) -> Self {
+        Self { particle, other, while_touching: false }
+    }
+
+    fn matches(&self, a: super::ParticleId, b: super::ParticleId) -> bool {
+        let matches_pair = |first: super::ParticleId, second: super::ParticleId|
+            self.particle.is_none_or(|p| p == first) && self.other.is_none_or(|p| p == second);
+
+        matches_pair(a, b) || matches_pair(b, a)
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for ParticleContactTrigger {
+    /// Both particles of each matching contact are causes.
+    fn check(&mut self, sim: &super::SimulationState, _dt: f32) -> Option<Vec<TriggerCause>> {
+        let particles = sim.contacts.iter()
+            .filter(|contact| self.while_touching || sim.is_new_contact(contact))
+            .filter_map(|contact| match *contact {
+                super::Contact::Particles { a, b } if self.matches(a, b) => Some([a, b]),
+                _ => None
+            })
+            .flatten();
+
+        collect_causes(sim, particles)
+    }
+}
+
+impl rendering::RenderableTool for ParticleContactTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("Particles touched");
+
+            egui::Grid::new(format!("particle-contact-settings{}", id_salt))
+                .show(ui, |ui| {
+                ui.label("Particle ID:");
+                changed |= filter_edit(ui, &mut self.particle, "Any");
+                ui.end_row();
+
+                ui.label("Other particle ID:");
+                changed |= filter_edit(ui, &mut self.other, "Any");
+                ui.end_row();
+
+                changed |= ui.checkbox(&mut self.while_touching, "Keep firing while touching").changed();
+            });
+            *id_salt += 1;
+            (changed, false)
+        })
+    }
+}
+
+/// Causes of several triggers, each particle listed once.
+fn merge_causes(causes: impl IntoIterator<Item = Vec<TriggerCause>>) -> Vec<TriggerCause> {
+    let mut merged: Vec<TriggerCause> = vec![];
+
+    for cause in causes.into_iter().flatten() {
+        if !merged.iter().any(|c| c.particle == cause.particle) {
+            merged.push(cause);
+        }
+    }
+
+    merged
+}
+
+/// Combo box that replaces `trigger` with a new one of the chosen kind.
+fn replace_trigger_edit(ui: &mut egui::Ui, trigger: &mut Box<dyn SimTrigger>, id_salt: &mut u32) -> bool {
+    let mut changed = false;
+
+    egui::ComboBox::new(format!("replace-trigger{}", id_salt), "")
+        .selected_text("Replace with...")
+        .show_ui(ui, |ui| {
+        for (name, create) in TRIGGER_KINDS {
+            if ui.selectable_label(false, name).clicked() {
+                *trigger = create();
+                changed = true;
+            }
+        }
+    });
+    *id_salt += 1;
+
+    changed
+}
+
+/// Editors for the conditions of a composite trigger, each with a remove button, and a combo box to add more.
+fn conditions_edit(ui: &mut egui::Ui, triggers: &mut Vec<Box<dyn SimTrigger>>, id_salt: &mut u32) -> bool {
+    let mut changed = false;
+    let mut remove = None;
+
+    for (i, trigger) in triggers.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            changed |= trigger.draw(ui, id_salt).inner.0;
+
+            if ui.button("X").on_hover_text("Remove condition").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = remove {
+        triggers.remove(i);
+        changed = true;
+    }
+
+    egui::ComboBox::new(format!("add-condition{}", id_salt), "")
+        .selected_text("+ Add condition")
+        .show_ui(ui, |ui| {
+        for (name, create) in TRIGGER_KINDS {
+            if ui.selectable_label(false, name).clicked() {
+                triggers.push(create());
+                changed = true;
+            }
+        }
+    });
+    *id_salt += 1;
+
+    changed
+}
+
+/// Fires when all of its conditions fire in the same step, caused by the causes of all of them.
+/// Without conditions it never fires.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct AllTrigger {
+    triggers: Vec<Box<dyn SimTrigger>>
+}
+
+impl AllTrigger {
+    pub fn new(triggers: Vec<Box<dyn SimTrigger>>) -> Self {
+        Self { triggers }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for AllTrigger {
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>> {
+        // Every condition is checked so stateful ones see every step
+        let results: Vec<_> = self.triggers.iter_mut().map(|t| t.check(sim, dt)).collect();
+
+        if results.is_empty() {
+            return None;
+        }
+
+        results.into_iter().collect::<Option<Vec<_>>>().map(merge_causes)
+    }
+}
+
+impl rendering::RenderableTool for AllTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("All of");
+            let changed = conditions_edit(ui, &mut self.triggers, id_salt);
+            (changed, false)
+        })
+    }
+}
+
+/// Fires when any of its conditions fires, caused by the causes of those that did.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct AnyTrigger {
+    triggers: Vec<Box<dyn SimTrigger>>
+}
+
+impl AnyTrigger {
+    pub fn new(triggers: Vec<Box<dyn SimTrigger>>) -> Self {
+        Self { triggers }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for AnyTrigger {
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>> {
+        let fired: Vec<_> = self.triggers.iter_mut().filter_map(|t| t.check(sim, dt)).collect();
+
+        (!fired.is_empty()).then(|| merge_causes(fired))
+    }
+}
+
+impl rendering::RenderableTool for AnyTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("Any of");
+            let changed = conditions_edit(ui, &mut self.triggers, id_salt);
+            (changed, false)
+        })
+    }
+}
+
+/// Fires, with no causes, on every step its condition doesn't fire.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotTrigger {
+    trigger: Box<dyn SimTrigger>
+}
+
+impl NotTrigger {
+    pub fn new(trigger: Box<dyn SimTrigger>) -> Self {
+        Self { trigger }
+    }
+}
+
+#[typetag::serde]
+impl SimTrigger for NotTrigger {
+    fn check(&mut self, sim: &super::SimulationState, dt: f32) -> Option<Vec<TriggerCause>> {
+        match self.trigger.check(sim, dt) {
+            Some(_) => None,
+            None => Some(vec![])
+        }
+    }
+
+    fn fires_on_most_steps(&self) -> bool { true }
+}
+
+impl rendering::RenderableTool for NotTrigger {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.heading("Not");
+
+            let mut changed = replace_trigger_edit(ui, &mut self.trigger, id_salt);
+            changed |= self.trigger.draw(ui, id_salt).inner.0;
+            (changed, false)
+        })
+    }
+}
+
+/// Which particles an event acts on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ParticleTarget {
+    /// The particles that made the trigger fire.
+    Causes,
+    All,
+    Particle(super::ParticleId)
+}
+
+impl ParticleTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParticleTarget::Causes => "Triggering particles",
+            ParticleTarget::All => "All particles",
+            ParticleTarget::Particle(_) => "Particle"
+        }
+    }
+
+    /// Indices into `sim.particles` of the targeted particles that still exist.
+    fn indices(&self, sim: &super::SimulationState, causes: &[TriggerCause]) -> Vec<usize> {
+        match self {
+            ParticleTarget::Causes => causes.iter().filter_map(|c| sim.particle_index(c.particle)).collect(),
+            ParticleTarget::All => (0..sim.particles.len()).collect(),
+            ParticleTarget::Particle(id) => sim.particle_index(*id).into_iter().collect()
+        }
+    }
+
+    fn edit(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> bool {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            let id = match self {
+                ParticleTarget::Particle(id) => *id,
+                _ => 0
+            };
+
+            egui::ComboBox::new(format!("particle-target{}", id_salt), "")
+                .selected_text(self.name())
+                .show_ui(ui, |ui| {
+                for target in [ParticleTarget::Causes, ParticleTarget::All, ParticleTarget::Particle(id)] {
+                    changed |= ui.selectable_value(self, target, target.name()).changed();
+                }
+            });
+            *id_salt += 1;
+
+            if let ParticleTarget::Particle(id) = self {
+                changed |= ui.add(egui::DragValue::new(id).prefix("ID:")).changed();
+            }
+        });
+
+        changed
+    }
+}
+
+/// The frame around an event editor with its heading and remove button. `add_contents` fills a grid and returns whether anything changed.
+fn event_editor(ui: &mut egui::Ui, heading: &str, id_salt: &mut u32, add_contents: impl FnOnce(&mut egui::Ui, &mut u32) -> bool) -> egui::InnerResponse<(bool, bool)> {
+    let mut changed = false;
+    let mut remove = false;
+
+    egui::Frame::group(ui.style())
+        .fill(egui::Color32::from_gray(30))
+        .corner_radius(5.0)
+        .inner_margin(10.0)
+        .show(ui, |ui| {
+
+        ui.horizontal(|ui| {
+            ui.heading(heading);
+
+            remove = ui.button("X").on_hover_text("Remove").clicked();
+        });
+
+        let grid_salt = *id_salt;
+        *id_salt += 1;
+
+        egui::Grid::new(format!("event-settings{}", grid_salt))
+            .show(ui, |ui| {
+            changed |= add_contents(ui, id_salt);
+        });
+        (changed, remove)
+    })
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RemoveEvent {
+    target: ParticleTarget
+}
+
+impl RemoveEvent {
+    pub fn new(target: ParticleTarget) -> Self {
+        Self { target }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for RemoveEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, causes: &[TriggerCause]) {
+        let remove: std::collections::HashSet<super::ParticleId> = self.target.indices(sim, causes).into_iter()
+            .map(|i| sim.particles[i].id())
+            .collect();
+
+        sim.particles.retain(|p| !remove.contains(&p.id()));
+    }
+}
+
+impl rendering::RenderableTool for RemoveEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Remove Particles", id_salt, |ui, id_salt| {
+            ui.label("Particles");
+            self.target.edit(ui, id_salt)
+        })
+    }
+}
+
+/// Sets the color of particles, or with `cycle` moves each one on to the color after its current one in `colors`.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ColorEvent {
+    target: ParticleTarget,
+    colors: Vec<egui::Color32>,
+    cycle: bool
+}
+
+impl ColorEvent {
+    pub fn new(target: ParticleTarget, colors: Vec<egui::Color32>, cycle: bool) -> Self {
+        Self { target, colors, cycle }
+    }
+
+    fn next_color(&self, current: egui::Color32) -> Option<egui::Color32> {
+        let first = *self.colors.first()?;
+
+        if !self.cycle {
+            return Some(first);
+        }
+
+        Some(match self.colors.iter().position(|&c| c == current) {
+            Some(i) => self.colors[(i + 1) % self.colors.len()],
+            None => first
+        })
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for ColorEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, causes: &[TriggerCause]) {
+        for i in self.target.indices(sim, causes) {
+            if let Some(color) = self.next_color(sim.particles[i].color) {
+                sim.particles[i].color = color;
+            }
+        }
+    }
+}
+
+impl rendering::RenderableTool for ColorEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Change Color", id_salt, |ui, id_salt| {
+            let mut changed = false;
+
+            ui.label("Particles");
+            changed |= self.target.edit(ui, id_salt);
+            ui.end_row();
+
+            changed |= ui.checkbox(&mut self.cycle, "Cycle through colors").changed();
+            ui.end_row();
+
+            ui.label(if self.cycle { "Colors" } else { "Color" });
+            ui.horizontal(|ui| {
+                let shown = if self.cycle { self.colors.len() } else { self.colors.len().min(1) };
+                let mut remove = None;
+
+                for (i, color) in self.colors.iter_mut().enumerate().take(shown) {
+                    let mut hsva = crate::util::color32_to_hsva(*color);
+                    changed |= ui.color_edit_button_hsva(&mut hsva).changed();
+                    *color = crate::util::hsva_to_color32(hsva);
+
+                    if self.cycle && shown > 1 && ui.small_button("-").on_hover_text("Remove color").clicked() {
+                        remove = Some(i);
+                    }
+                }
+
+                if let Some(i) = remove {
+                    self.colors.remove(i);
+                    changed = true;
+                }
+
+                if (self.cycle || self.colors.is_empty()) && ui.small_button("+").on_hover_text("Add color").clicked() {
+                    self.colors.push(self.colors.last().copied().unwrap_or(egui::Color32::WHITE));
+                    changed = true;
+                }
+            });
+            changed
+        })
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScaleRadiusEvent {
+    target: ParticleTarget,
+    factor: f32
+}
+
+impl ScaleRadiusEvent {
+    pub fn new(target: ParticleTarget, factor: f32) -> Self {
+        Self { target, factor }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for ScaleRadiusEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, causes: &[TriggerCause]) {
+        for i in self.target.indices(sim, causes) {
+            sim.particles[i].radius *= self.factor;
+        }
+    }
+}
+
+impl rendering::RenderableTool for ScaleRadiusEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Scale Radius", id_salt, |ui, id_salt| {
+            let mut changed = false;
+
+            ui.label("Particles");
+            changed |= self.target.edit(ui, id_salt);
+            ui.end_row();
+
+            ui.label("Factor");
+            changed |= ui.add(egui::DragValue::new(&mut self.factor).speed(0.01).range(0.0..=f32::INFINITY).prefix("x")).changed();
+            changed
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum VelocityChange {
+    /// Divided by each particle's mass.
+    Impulse,
+    Add,
+    Set
+}
+
+impl VelocityChange {
+    pub const ALL: [VelocityChange; 3] = [VelocityChange::Impulse, VelocityChange::Add, VelocityChange::Set];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VelocityChange::Impulse => "Apply impulse",
+            VelocityChange::Add => "Add velocity",
+            VelocityChange::Set => "Set velocity"
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct VelocityEvent {
+    target: ParticleTarget,
+    change: VelocityChange,
+    value: glam::Vec2
+}
+
+impl VelocityEvent {
+    pub fn new(target: ParticleTarget, change: VelocityChange, value: glam::Vec2) -> Self {
+        Self { target, change, value }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for VelocityEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, causes: &[TriggerCause]) {
+        for i in self.target.indices(sim, causes) {
+            let particle = &mut sim.particles[i];
+
+            match self.change {
+                VelocityChange::Impulse => particle.apply_impulse(self.value),
+                VelocityChange::Add => particle.set_velocity(particle.velocity() + self.value),
+                VelocityChange::Set => particle.set_velocity(self.value)
+            }
+        }
+    }
+}
+
+impl rendering::RenderableTool for VelocityEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Change Velocity", id_salt, |ui, id_salt| {
+            let mut changed = false;
+
+            ui.label("Particles");
+            changed |= self.target.edit(ui, id_salt);
+            ui.end_row();
+
+            ui.label("Change");
+            egui::ComboBox::new(format!("velocity-change{}", id_salt), "")
+                .selected_text(self.change.name())
+                .show_ui(ui, |ui| {
+                for change in VelocityChange::ALL {
+                    changed |= ui.selectable_value(&mut self.change, change, change.name()).changed();
+                }
+            });
+            *id_salt += 1;
+            ui.end_row();
+
+            ui.label(if self.change == VelocityChange::Impulse { "Impulse" } else { "Velocity" });
+            ui.horizontal(|ui| {
+                changed |= ui.add(egui::DragValue::new(&mut self.value.x).prefix("X:").speed(0.01)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut self.value.y).prefix("Y:").speed(0.01)).changed();
+            });
+            changed
+        })
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct SetGravityEvent {
+    gravity: glam::Vec2
+}
+
+impl SetGravityEvent {
+    pub fn new(gravity: glam::Vec2) -> Self {
+        Self { gravity }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for SetGravityEvent {
+    /// Keyframes on the gravity tracks still override this at the next step.
+    fn trigger(&self, sim: &mut super::SimulationState, _causes: &[TriggerCause]) {
+        sim.gravity_accel = self.gravity;
+    }
+
+    fn changes_scene(&self) -> bool { true }
+}
+
+impl rendering::RenderableTool for SetGravityEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Set Gravity", id_salt, |ui, _| {
+            let mut changed = false;
+
+            ui.label("Gravity");
+            ui.horizontal(|ui| {
+                changed |= ui.add(egui::DragValue::new(&mut self.gravity.x).prefix("X:").speed(0.01)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut self.gravity.y).prefix("Y:").speed(0.01)).changed();
+            });
+            changed
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Switch {
+    Toggle,
+    On,
+    Off
+}
+
+impl Switch {
+    pub const ALL: [Switch; 3] = [Switch::Toggle, Switch::On, Switch::Off];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Switch::Toggle => "Toggle",
+            Switch::On => "Turn on",
+            Switch::Off => "Turn off"
+        }
+    }
+
+    pub fn apply(&self, value: &mut bool) {
+        *value = match self {
+            Switch::Toggle => !*value,
+            Switch::On => true,
+            Switch::Off => false
+        };
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ParticleCollisionsEvent {
+    switch: Switch
+}
+
+impl ParticleCollisionsEvent {
+    pub fn new(switch: Switch) -> Self {
+        Self { switch }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for ParticleCollisionsEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, _causes: &[TriggerCause]) {
+        self.switch.apply(&mut sim.particle_collisions);
+    }
+
+    fn changes_scene(&self) -> bool { true }
+}
+
+impl rendering::RenderableTool for ParticleCollisionsEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Particle Collisions", id_salt, |ui, id_salt| {
+            let mut changed = false;
+
+            ui.label("Collisions");
+            egui::ComboBox::new(format!("collisions-switch{}", id_salt), "")
+                .selected_text(self.switch.name())
+                .show_ui(ui, |ui| {
+                for switch in Switch::ALL {
+                    changed |= ui.selectable_value(&mut self.switch, switch, switch.name()).changed();
+                }
+            });
+            *id_salt += 1;
+            changed
+        })
+    }
+}
+
+/// Removes the constraint with the given id, if it is still there.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RemoveConstraintEvent {
+    /// See [`super::SceneConstraint::id`].
+    constraint: super::ConstraintId
+}
+
+impl RemoveConstraintEvent {
+    pub fn new(constraint: super::ConstraintId) -> Self {
+        Self { constraint }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for RemoveConstraintEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, _causes: &[TriggerCause]) {
+        if let Some(index) = sim.constraint_index(self.constraint) {
+            sim.constraints.remove(index);
+        }
+    }
+
+    fn changes_scene(&self) -> bool { true }
+}
+
+impl rendering::RenderableTool for RemoveConstraintEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Remove Constraint", id_salt, |ui, _| {
+            ui.label("Constraint ID");
+            ui.add(egui::DragValue::new(&mut self.constraint).speed(0.1)).changed()
+        })
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct AddConstraintEvent {
+    constraint: Box<dyn super::Constraint>
+}
+
+impl AddConstraintEvent {
+    pub fn new(constraint: Box<dyn super::Constraint>) -> Self {
+        Self { constraint }
+    }
+}
+
+#[typetag::serde]
+impl SimEvent for AddConstraintEvent {
+    fn trigger(&self, sim: &mut super::SimulationState, _causes: &[TriggerCause]) {
+        sim.add_boxed_constraint(self.constraint.clone());
+    }
+
+    fn tracks(&self) -> Option<&super::animation::Tracks> {
+        self.constraint.tracks()
+    }
+
+    fn changes_scene(&self) -> bool { true }
+}
+
+impl rendering::RenderableTool for AddConstraintEvent {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        event_editor(ui, "Add Constraint", id_salt, |ui, id_salt| {
+            let mut changed = false;
+
+            ui.label("Kind");
+            egui::ComboBox::new(format!("add-constraint-kind{}", id_salt), "")
+                .selected_text("Replace with...")
+                .show_ui(ui, |ui| {
+                if ui.selectable_label(false, "Circle").clicked() {
+                    self.constraint = Box::new(super::constraints::CircleConstraint::default());
+                    changed = true;
+                }
+
+                if ui.selectable_label(false, "Circle With Hole").clicked() {
+                    self.constraint = Box::new(super::constraints::HoleCircleConstraint::default());
+                    changed = true;
+                }
+            });
+            *id_salt += 1;
+            ui.end_row();
+
+            // The constraint's own remove button has nothing to remove here
+            changed |= self.constraint.draw(ui, id_salt).inner.0;
+            changed
+        })
+    }
+}
@@ -0,0 +1,258 @@
+use super::{Particle, ParticleId};
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RodLink {
+    a: ParticleId,
+    b: ParticleId,
+    length: f32,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RopeLink {
+    a: ParticleId,
+    b: ParticleId,
+    length: f32,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct SpringLink {
+    a: ParticleId,
+    b: ParticleId,
+    rest_length: f32,
+    stiffness: f32,
+    damping: f32,
+}
+
+impl RodLink {
+    pub fn new(a: ParticleId, b: ParticleId, length: f32) -> Self {
+        Self { a, b, length }
+    }
+}
+
+impl Default for RodLink {
+    fn default() -> Self {
+        Self { a: 0, b: 1, length: 0.25 }
+    }
+}
+
+impl RopeLink {
+    pub fn new(a: ParticleId, b: ParticleId, length: f32) -> Self {
+        Self { a, b, length }
+    }
+}
+
+impl Default for RopeLink {
+    fn default() -> Self {
+        Self { a: 0, b: 1, length: 0.25 }
+    }
+}
+
+impl SpringLink {
+    pub fn new(a: ParticleId, b: ParticleId, rest_length: f32, stiffness: f32, damping: f32) -> Self {
+        Self { a, b, rest_length, stiffness, damping }
+    }
+}
+
+impl Default for SpringLink {
+    fn default() -> Self {
+        Self { a: 0, b: 1, rest_length: 0.25, stiffness: 10.0, damping: 0.5 }
+    }
+}
+
+/// Moves `a` and `b` (weighted by inverse mass) so that they end up `length` apart.
+/// With `slack` set, only pulls them together, like a rope.
+fn project_distance(a: &mut Particle, b: &mut Particle, length: f32, slack: bool) {
+    let delta = b.position - a.position;
+    let dist = delta.length();
+    let inv_mass_sum = a.inverse_mass() + b.inverse_mass();
+
+    if dist == 0.0 || inv_mass_sum == 0.0 {
+        return;
+    }
+
+    let error = dist - length;
+    if slack && error <= 0.0 {
+        return;
+    }
+
+    let correction = (delta / dist) * (error / inv_mass_sum);
+
+    a.position += correction * a.inverse_mass();
+    b.position -= correction * b.inverse_mass();
+}
+
+#[typetag::serde]
+impl super::LinkConstraint for RodLink {
+    fn particles(&self) -> (ParticleId, ParticleId) {
+        (self.a, self.b)
+    }
+
+    fn constrain(&self, a: &mut Particle, b: &mut Particle) {
+        project_distance(a, b, self.length, false);
+    }
+}
+
+#[typetag::serde]
+impl super::LinkConstraint for RopeLink {
+    fn particles(&self) -> (ParticleId, ParticleId) {
+        (self.a, self.b)
+    }
+
+    fn constrain(&self, a: &mut Particle, b: &mut Particle) {
+        project_distance(a, b, self.length, true);
+    }
+
+    fn draw_sim(&self, a: &Particle, b: &Particle, renderer: &mut dyn super::rendering::SimRenderer, _render_state: &super::rendering::RenderState) {
+        let color = if a.position.distance(b.position) < self.length { egui::Color32::DARK_GRAY } else { egui::Color32::GRAY };
+        renderer.line_segment(a.position, b.position, 0.01, color);
+    }
+}
+
+#[typetag::serde]
+impl super::LinkConstraint for SpringLink {
+    fn particles(&self) -> (ParticleId, ParticleId) {
+        (self.a, self.b)
+    }
+
+    fn apply_forces(&self, a: &mut Particle, b: &mut Particle, dt: f32) {
+        let delta = b.position - a.position;
+        let dist = delta.length();
+
+        if dist == 0.0 {
+            return;
+        }
+
+        let dir = delta / dist;
+        let closing_speed = (b.velocity() - a.velocity()).dot(dir);
+        let force = self.stiffness * (dist - self.rest_length) + self.damping * closing_speed;
+
+        a.apply_impulse(dir * force * dt);
+        b.apply_impulse(-dir * force * dt);
+    }
+
+    fn draw_sim(&self, a: &Particle, b: &Particle, renderer: &mut dyn super::rendering::SimRenderer, _render_state: &super::rendering::RenderState) {
+        const COILS: u32 = 8;
+        const WIDTH: f32 = 0.02;
+
+        let delta = b.position - a.position;
+        let normal = delta.perp().normalize_or_zero() * WIDTH;
+
+        let mut last = a.position;
+        for i in 1..=(2 * COILS) {
+            let t = i as f32 / (2 * COILS) as f32;
+            let side = if i == 2 * COILS { 0.0 } else if i % 2 == 0 { -1.0 } else { 1.0 };
+            let this = a.position + delta * t + normal * side;
+
+            renderer.line_segment(last, this, 0.005, egui::Color32::LIGHT_BLUE);
+            last = this;
+        }
+    }
+}
+
+fn draw_link_ids(ui: &mut egui::Ui, a: &mut ParticleId, b: &mut ParticleId) -> bool {
+    let mut changed = false;
+
+    ui.label("Particles");
+    changed |= ui.add(egui::DragValue::new(a).prefix("A:").speed(0.1)).changed();
+    changed |= ui.add(egui::DragValue::new(b).prefix("B:").speed(0.1)).changed();
+    ui.end_row();
+
+    changed
+}
+
+impl super::rendering::RenderableTool for RodLink {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+        let mut remove = false;
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.horizontal(|ui| {
+                ui.heading("Rod");
+
+                remove = ui.button("X").on_hover_text("Remove").clicked();
+            });
+
+            egui::Grid::new(format!("rod-settings{}", id_salt))
+                .show(ui, |ui| {
+
+                changed |= draw_link_ids(ui, &mut self.a, &mut self.b);
+
+                ui.label("Length:");
+                changed |= ui.add(egui::DragValue::new(&mut self.length).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+            });
+            *id_salt += 1;
+            (changed, remove)
+        })
+    }
+}
+
+impl super::rendering::RenderableTool for RopeLink {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+        let mut remove = false;
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.horizontal(|ui| {
+                ui.heading("Rope");
+
+                remove = ui.button("X").on_hover_text("Remove").clicked();
+            });
+
+            egui::Grid::new(format!("rope-settings{}", id_salt))
+                .show(ui, |ui| {
+
+                changed |= draw_link_ids(ui, &mut self.a, &mut self.b);
+
+                ui.label("Max length:");
+                changed |= ui.add(egui::DragValue::new(&mut self.length).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+            });
+            *id_salt += 1;
+            (changed, remove)
+        })
+    }
+}
+
+impl super::rendering::RenderableTool for SpringLink {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+        let mut remove = false;
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.horizontal(|ui| {
+                ui.heading("Spring");
+
+                remove = ui.button("X").on_hover_text("Remove").clicked();
+            });
+
+            egui::Grid::new(format!("spring-settings{}", id_salt))
+                .show(ui, |ui| {
+
+                changed |= draw_link_ids(ui, &mut self.a, &mut self.b);
+
+                ui.label("Rest length:");
+                changed |= ui.add(egui::DragValue::new(&mut self.rest_length).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+
+                ui.end_row();
+
+                ui.label("Stiffness:");
+                changed |= ui.add(egui::DragValue::new(&mut self.stiffness).speed(0.1).range(0.0..=f32::INFINITY)).changed();
+
+                ui.end_row();
+
+                ui.label("Damping:");
+                changed |= ui.add(egui::DragValue::new(&mut self.damping).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+            });
+            *id_salt += 1;
+            (changed, remove)
+        })
+    }
+}
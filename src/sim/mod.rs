@@ -1,53 +1,357 @@
 pub mod rendering;
 pub mod event;
 pub mod constraints;
+pub mod links;
+pub mod animation;
+pub mod scene;
+pub mod cache;
 
-#[derive(Clone, PartialEq)]
+/// Stable identity of a particle within a simulation, assigned by [`SimulationState::add_particle`].
+pub type ParticleId = u64;
+
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Particle {
+    id: ParticleId,
+
     pub position: glam::Vec2,
     pub last_position: glam::Vec2,
     pub radius: f32,
-    pub color: egui::Color32
+    pub color: egui::Color32,
+
+    pub mass: f32,
+    pub restitution: f32,
+    pub friction: f32,
+
+    velocity: glam::Vec2,
+    #[serde(skip)]
+    velocity_overridden: bool
 }
 
 impl Particle {
     pub fn new(position: glam::Vec2, radius: f32, color: egui::Color32) -> Self {
         Self {
+            id: 0,
             position,
             last_position: position,
-            radius, color
+            radius, color,
+            mass: 1.0,
+            restitution: 1.0,
+            friction: 0.0,
+            velocity: glam::Vec2::ZERO,
+            velocity_overridden: false
+        }
+    }
+
+    pub fn with_velocity(mut self, velocity: glam::Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn id(&self) -> ParticleId {
+        self.id
+    }
+
+    /// Velocity in sim units per second.
+    pub fn velocity(&self) -> glam::Vec2 {
+        self.velocity
+    }
+
+    /// Sets the velocity explicitly. During a step this takes precedence over the velocity
+    /// derived from the position change, so constraints can use it to respond to collisions.
+    pub fn set_velocity(&mut self, velocity: glam::Vec2) {
+        self.velocity = velocity;
+        self.velocity_overridden = true;
+    }
+
+    /// Changes the velocity by `impulse` divided by the mass.
+    pub fn apply_impulse(&mut self, impulse: glam::Vec2) {
+        self.velocity += impulse * self.inverse_mass();
+    }
+
+    /// Zero for particles with non-positive mass, which are pinned in place.
+    pub fn inverse_mass(&self) -> f32 {
+        if self.mass > 0.0 { 1.0 / self.mass } else { 0.0 }
+    }
+
+    /// Reflects the velocity off a surface with the given normal (pointing away from the surface).
+    /// The normal component is scaled by the wall's `elasticity` times the particle's restitution, and
+    /// the tangential component is slowed by the particle's friction. Does nothing if the particle is already moving away.
+    pub fn bounce(&mut self, normal: glam::Vec2, elasticity: f32) {
+        self.bounce_moving(normal, glam::Vec2::ZERO, elasticity, 0.0);
+    }
+
+    /// Like [`Particle::bounce`], but off a surface moving at `surface_velocity`. The response is computed relative to the
+    /// surface, using the larger of the particle's and the surface's friction, so a moving wall drags particles along.
+    pub fn bounce_moving(&mut self, normal: glam::Vec2, surface_velocity: glam::Vec2, elasticity: f32, surface_friction: f32) {
+        let relative_velocity = self.velocity - surface_velocity;
+        let normal_speed = relative_velocity.dot(normal);
+
+        if normal_speed < 0.0 {
+            let restitution = elasticity * self.restitution;
+            let normal_impulse = -(1.0 + restitution) * normal_speed;
+
+            let tangent_velocity = relative_velocity - normal_speed * normal;
+            let tangent_speed = tangent_velocity.length();
+            let friction_loss = (self.friction.max(surface_friction) * normal_impulse).min(tangent_speed);
+            let tangent_velocity = if tangent_speed > 0.0 { tangent_velocity * (1.0 - friction_loss / tangent_speed) } else { tangent_velocity };
+
+            self.set_velocity(surface_velocity + tangent_velocity - restitution * normal_speed * normal);
         }
     }
 }
 
+impl rendering::RenderableTool for Particle {
+    fn draw(&mut self, ui: &mut egui::Ui, id_salt: &mut u32) -> egui::InnerResponse<(bool, bool)> {
+        let mut changed = false;
+        let mut remove = false;
+
+        egui::Frame::group(ui.style())
+            .corner_radius(5.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+
+            ui.horizontal(|ui| {
+                ui.heading(format!("Particle {}", self.id));
+
+                remove = ui.button("X").on_hover_text("Remove").clicked();
+            });
+
+            egui::Grid::new(format!("initial-particle-settings{}", id_salt))
+                .show(ui, |ui| {
+
+                ui.label("Position");
+                changed |= ui.add(egui::DragValue::new(&mut self.position.x).prefix("X:").speed(0.01)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut self.position.y).prefix("Y:").speed(0.01)).changed();
+                self.last_position = self.position;
+                ui.end_row();
+
+                ui.label("Velocity");
+                changed |= ui.add(egui::DragValue::new(&mut self.velocity.x).prefix("X:").speed(0.01)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut self.velocity.y).prefix("Y:").speed(0.01)).changed();
+                ui.end_row();
+
+                ui.label("Radius");
+                changed |= ui.add(egui::DragValue::new(&mut self.radius).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+                ui.end_row();
+
+                ui.label("Mass");
+                changed |= ui.add(egui::DragValue::new(&mut self.mass).speed(0.01).range(0.0..=f32::INFINITY))
+                    .on_hover_text("0 pins the particle in place").changed();
+                ui.end_row();
+
+                ui.label("Restitution");
+                changed |= ui.add(egui::DragValue::new(&mut self.restitution).speed(0.01).range(0.0..=1.0)).changed();
+                ui.end_row();
+
+                ui.label("Friction");
+                changed |= ui.add(egui::DragValue::new(&mut self.friction).speed(0.01).range(0.0..=f32::INFINITY)).changed();
+                ui.end_row();
+
+                ui.label("Color");
+
+                let mut hsva: egui::epaint::Hsva = crate::util::color32_to_hsva(self.color);
+
+                if ui.color_edit_button_hsva(&mut hsva).changed() {
+                    self.color = crate::util::hsva_to_color32(hsva);
+                    changed = true;
+                }
+            });
+            *id_salt += 1;
+            (changed, remove)
+        })
+    }
+}
+
+#[typetag::serde(tag = "type")]
 pub trait Constraint: Send + dyn_clone::DynClone + rendering::RenderableTool {
-    fn constrain(&self, particle: &mut Particle);
-    fn draw_sim(&self, _renderer: &dyn rendering::SimRenderer, _ui: &mut egui::Ui, _r: &rendering::RenderState) {}
+    /// `time` is the simulation time in seconds at the end of the current substep.
+    /// Returns whether the particle touched the constraint and was pushed back, which is recorded as a [`Contact`].
+    fn constrain(&self, particle: &mut Particle, time: f32) -> bool;
+
+    /// Updates keyframed parameters to their values at `time`.
+    fn animate(&mut self, _time: f32) {}
+    fn tracks(&self) -> Option<&animation::Tracks> { None }
+    fn draw_sim(&self, _renderer: &mut dyn rendering::SimRenderer, _render_state: &rendering::RenderState) {}
+
+    /// Points in sim units that can be dragged in the preview to edit the constraint, as it is at `time`.
+    fn handles(&self, _time: f32) -> Vec<glam::Vec2> { vec![] }
+    /// Moves handle `index` of [`Constraint::handles`] to `to`.
+    fn drag_handle(&mut self, _index: usize, _to: glam::Vec2, _time: f32) {}
 }
 dyn_clone::clone_trait_object!(Constraint);
 
-#[derive(Clone)]
+pub type ConstraintId = u64;
+
+/// A constraint placed in the scene, with the id that triggers and events refer to it by.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct SceneConstraint {
+    id: ConstraintId,
+    pub constraint: Box<dyn Constraint>
+}
+
+impl SceneConstraint {
+    pub fn id(&self) -> ConstraintId {
+        self.id
+    }
+}
+
+impl std::ops::Deref for SceneConstraint {
+    type Target = dyn Constraint;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.constraint
+    }
+}
+
+impl std::ops::DerefMut for SceneConstraint {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.constraint
+    }
+}
+
+/// A constraint between two particles, referenced by their [`ParticleId`]s.
+/// Links whose particles no longer exist are skipped.
+#[typetag::serde(tag = "type")]
+pub trait LinkConstraint: Send + dyn_clone::DynClone + rendering::RenderableTool {
+    fn particles(&self) -> (ParticleId, ParticleId);
+
+    /// Position-level correction, run once per constraint iteration.
+    fn constrain(&self, _a: &mut Particle, _b: &mut Particle) {}
+    /// Velocity-level forces, run once per substep before integration.
+    fn apply_forces(&self, _a: &mut Particle, _b: &mut Particle, _dt: f32) {}
+
+    fn draw_sim(&self, a: &Particle, b: &Particle, renderer: &mut dyn rendering::SimRenderer, _render_state: &rendering::RenderState) {
+        renderer.line_segment(a.position, b.position, 0.01, egui::Color32::GRAY);
+    }
+}
+dyn_clone::clone_trait_object!(LinkConstraint);
+
+/// Walks two serialized states side by side for [`SimulationState::first_change_time`].
+fn first_change_in(old: &serde_json::Value, new: &serde_json::Value) -> Option<f32> {
+    use serde_json::Value;
+
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) if a.keys().eq(b.keys()) => {
+            a.iter().zip(b.values())
+                .filter_map(|((key, a), b)| {
+                    if key == "tracks" {
+                        let a: animation::Tracks = serde_json::from_value(a.clone()).ok()?;
+                        let b: animation::Tracks = serde_json::from_value(b.clone()).ok()?;
+                        a.first_change_time(&b)
+                    } else {
+                        first_change_in(a, b)
+                    }
+                })
+                .min_by(f32::total_cmp)
+        },
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            a.iter().zip(b).filter_map(|(a, b)| first_change_in(a, b)).min_by(f32::total_cmp)
+        },
+        _ => (old != new).then_some(0.0)
+    }
+}
+
+fn particle_pair_mut(particles: &mut [Particle], i: usize, j: usize) -> Option<(&mut Particle, &mut Particle)> {
+    if i == j || i >= particles.len() || j >= particles.len() {
+        return None;
+    }
+
+    if i < j {
+        let (head, tail) = particles.split_at_mut(j);
+        Some((&mut head[i], &mut tail[0]))
+    } else {
+        let (head, tail) = particles.split_at_mut(i);
+        Some((&mut tail[0], &mut head[j]))
+    }
+}
+
+/// A contact resolved during the last substep, see [`SimulationState::contacts`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Contact {
+    /// The particle touched the constraint with id `constraint`.
+    Constraint { constraint: ConstraintId, particle: ParticleId },
+    /// Two particles touched, `a` being the one with the lower id.
+    Particles { a: ParticleId, b: ParticleId }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct SimulationState {
     pub particles: Vec<Particle>,
-    pub constraints: Vec<Box<dyn Constraint>>,
+    pub constraints: Vec<SceneConstraint>,
+    pub links: Vec<Box<dyn LinkConstraint>>,
     
     pub trigger_managers: Vec<event::TriggerManager>,
 
     pub gravity_accel: glam::Vec2,
 
+    /// Simulation time in seconds.
+    pub time: f32,
+
+    /// Keyframe tracks for scene parameters (`gravity_x`, `gravity_y`, `camera_x`, `camera_y`, `camera_zoom`).
+    pub tracks: animation::Tracks,
+
+    pub camera: rendering::Camera,
+
     pub particle_collisions: bool,
+    pub collision_broadphase: CollisionBroadphase,
+
+    pub substeps: u32,
+    pub constraint_iterations: u32,
+
+    /// Contacts resolved during the last substep, each listed once, for collision triggers to react to in the next one.
+    #[serde(skip)]
+    pub contacts: Vec<Contact>,
+    /// Contacts of the substep before, to tell new contacts from resting ones.
+    #[serde(skip)]
+    previous_contacts: Vec<Contact>,
+
+    next_particle_id: ParticleId,
+    #[serde(default)]
+    next_constraint_id: ConstraintId,
+    #[serde(default)]
+    next_trigger_id: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum CollisionBroadphase {
+    BruteForce,
+    SpatialHash
+}
+
+impl CollisionBroadphase {
+    pub const ALL: [CollisionBroadphase; 2] = [CollisionBroadphase::BruteForce, CollisionBroadphase::SpatialHash];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CollisionBroadphase::BruteForce => "Brute force",
+            CollisionBroadphase::SpatialHash => "Spatial hash"
+        }
+    }
 }
 
+/// Rate at which simulation frames are produced, in frames per second.
+pub const FRAME_RATE: f32 = 60.0;
+
 pub enum SimulationCommand {
     RequestFrame(u32),
     StoreFrame(u32, SimulationState),
+    /// Drops the cached frames from the given one onward and continues the simulation from the frame before it,
+    /// switched over to the scene definition of the given state. See [`SimulationState::apply_scene`].
+    InvalidateFrom(u32, SimulationState),
     GetCached,
-    ClearCache
+    ClearCache,
+    /// Sets the frame cache's memory budget in bytes.
+    SetCacheBudget(usize),
+    /// Sends the frame cache's checkpoints, in order, to the given channel. See [`cache::FrameCache::snapshot`].
+    SnapshotCache(flume::Sender<Vec<(u32, SimulationState)>>)
 }
 
 pub enum SimulationResponse {
-    Frame(u32, SimulationState),
-    Cached(u32)
+    Frame(u32, Box<SimulationState>),
+    Cached(u32),
+    /// Estimated memory used by the frame cache and its budget, in bytes.
+    CacheUsage { used: usize, budget: usize }
 }
 
 pub struct SimulationInterface {
@@ -55,15 +359,14 @@ pub struct SimulationInterface {
     manager_rx: flume::Receiver<SimulationResponse>,
 
     frame_cache: std::collections::BTreeMap<u32, SimulationState>,
-    manager_cached: u32
+    manager_cached: u32,
+    cache_usage: (usize, usize)
 }
 
 pub struct SimulationManager {
-    frame_cache: Vec<SimulationState>,
+    frame_cache: cache::FrameCache,
     requested_frame: Option<u32>,
 
-    fps: f32,
-
     interface_tx: flume::Sender<SimulationResponse>,
     interface_rx: flume::Receiver<SimulationCommand>
 }
@@ -79,10 +382,13 @@ impl<T: Send + 'static> EasySend<T> for flume::Sender<T> {
 }
 
 impl SimulationInterface {
+    const LOCAL_CACHE_FRAMES: usize = 8;
+
     pub fn new(manager_tx: flume::Sender<SimulationCommand>, manager_rx: flume::Receiver<SimulationResponse>) -> Self {
         Self {
             manager_tx, manager_rx, frame_cache: std::collections::BTreeMap::new(),
-            manager_cached: 0
+            manager_cached: 0,
+            cache_usage: (0, cache::FrameCache::DEFAULT_BUDGET)
         }
     }
 
@@ -97,8 +403,16 @@ impl SimulationInterface {
     }
 
     pub fn store_frame(&mut self, frame: u32, state: SimulationState) {
+        self.frame_cache.split_off(&frame);
+        self.frame_cache.insert(frame, state.clone());
         let request = SimulationCommand::StoreFrame(frame, state);
-        self.frame_cache.split_off(&(frame + 1));
+        self.manager_tx.ez_send(request);
+    }
+
+    /// Resimulates from `frame` onward with the edited `scene`, keeping every earlier frame.
+    pub fn invalidate_from(&mut self, frame: u32, scene: SimulationState) {
+        let request = SimulationCommand::InvalidateFrom(frame, scene);
+        self.frame_cache.split_off(&frame);
         self.manager_tx.ez_send(request);
     }
 
@@ -107,25 +421,44 @@ impl SimulationInterface {
         self.frame_cache = std::collections::BTreeMap::new();
     }
 
+    pub fn set_cache_budget(&mut self, bytes: usize) {
+        self.manager_tx.ez_send(SimulationCommand::SetCacheBudget(bytes));
+    }
+
+    /// Asks the manager for a copy of its frame cache checkpoints. The reply arrives on the returned channel.
+    pub fn snapshot_cache(&mut self) -> flume::Receiver<Vec<(u32, SimulationState)>> {
+        let (tx, rx) = flume::bounded(1);
+        self.manager_tx.ez_send(SimulationCommand::SnapshotCache(tx));
+        rx
+    }
+
     pub fn clear_local_cache(&mut self) {
         self.frame_cache = std::collections::BTreeMap::new();
     }
 
     pub fn process_requests(&mut self) {
-        loop {
-            if let Ok(res) = self.manager_rx.try_recv() {
-                match res {
-                    SimulationResponse::Frame(idx, frame) => {
-                        let _ = self.frame_cache.insert(idx, frame);
-                    },
-                    SimulationResponse::Cached(count) => {
-                        self.manager_cached = count;
-                        self.frame_cache.split_off(&(count + 1));
-                    },
-                    #[allow(unreachable_patterns)]
-                    _ => log::warn!("Unhandled response!")
-                }
-            } else { break; }
+        while let Ok(res) = self.manager_rx.try_recv() {
+            match res {
+                SimulationResponse::Frame(idx, frame) => {
+                    let _ = self.frame_cache.insert(idx, *frame);
+
+                    // Only a few frames around the playhead are kept here, the manager holds the rest
+                    while self.frame_cache.len() > Self::LOCAL_CACHE_FRAMES {
+                        let first = *self.frame_cache.first_key_value().unwrap().0;
+                        let last = *self.frame_cache.last_key_value().unwrap().0;
+                        self.frame_cache.remove(if idx - first > last - idx { &first } else { &last });
+                    }
+                },
+                SimulationResponse::Cached(count) => {
+                    self.manager_cached = count;
+                    self.frame_cache.split_off(&(count + 1));
+                },
+                SimulationResponse::CacheUsage { used, budget } => {
+                    self.cache_usage = (used, budget);
+                },
+                #[allow(unreachable_patterns)]
+                _ => log::warn!("Unhandled response!")
+            }
         }
     }
 
@@ -136,83 +469,89 @@ impl SimulationInterface {
     pub fn get_cached(&mut self) -> u32 {
         self.manager_cached
     }
+
+    /// Estimated memory used by the manager's frame cache and its budget, in bytes, as of the last [`SimulationInterface::load_cached`].
+    pub fn cache_usage(&self) -> (usize, usize) {
+        self.cache_usage
+    }
 }
 
 impl SimulationManager {
     pub fn new(interface_tx: flume::Sender<SimulationResponse>, interface_rx: flume::Receiver<SimulationCommand>) -> Self {
         Self {
-            frame_cache: vec![],
+            frame_cache: cache::FrameCache::new(FRAME_RATE),
             requested_frame: None,
-            fps: 60.0,
             interface_tx, interface_rx
         }
     }
 
     pub fn run_frame(&mut self) {
-        let mut last_frame = (self.frame_cache.last()).unwrap_or(&SimulationState::new()).clone();
-
-        last_frame.single_step(1.0 / self.fps);
-
-        self.frame_cache.push(last_frame);
+        self.frame_cache.step();
     }
 
     pub fn get_frame(&mut self, frame: u32) -> &SimulationState {
-        while self.frame_cache.len() as u32 <= frame {
-            self.run_frame();
-        }
-
-        &self.frame_cache[frame as usize]
+        self.frame_cache.get(frame)
     }
 
-    pub fn get_frame_mut(&mut self, frame: u32) -> &mut SimulationState {
-        self.frame_cache.truncate(frame as usize + 1);
+    /// Frames that were never simulated don't need to be, the last simulated one is switched over instead.
+    fn invalidate_from(&mut self, frame: u32, scene: SimulationState) {
+        let keep = frame.min(self.frame_cache.len());
 
-        while self.frame_cache.len() as u32 <= frame {
-            self.run_frame();
+        if keep == 0 {
+            self.frame_cache.store(0, scene);
+            return;
         }
 
-        &mut self.frame_cache[frame as usize]
+        let mut state = self.frame_cache.get(keep - 1).clone();
+        state.apply_scene(&scene);
+        self.frame_cache.store(keep - 1, state);
     }
 
     pub fn process_requests(&mut self) {
-        loop {
-            if let Ok(cmd) = self.interface_rx.try_recv() {
-                match cmd {
-                    SimulationCommand::RequestFrame(frame_idx) => {
-                        self.requested_frame = Some(frame_idx);
-                    },
-                    SimulationCommand::StoreFrame(frame_idx, state) => {
-                        let frame = self.get_frame_mut(frame_idx);
-                        *frame = state;
-                    },
-                    SimulationCommand::ClearCache => {
-                        self.frame_cache = vec![]
-                    },
-                    SimulationCommand::GetCached => {
-                        let res = SimulationResponse::Cached(self.frame_cache.len() as u32);
-
-                        self.interface_tx.ez_send(res);
-                    }
-                    #[allow(unreachable_patterns)]
-                    _ => log::warn!("Unhandled simulation command !")
+        while let Ok(cmd) = self.interface_rx.try_recv() {
+            match cmd {
+                SimulationCommand::RequestFrame(frame_idx) => {
+                    self.requested_frame = Some(frame_idx);
+                },
+                SimulationCommand::StoreFrame(frame_idx, state) => {
+                    self.frame_cache.store(frame_idx, state);
+                },
+                SimulationCommand::InvalidateFrom(frame_idx, scene) => {
+                    self.invalidate_from(frame_idx, scene);
+                },
+                SimulationCommand::ClearCache => {
+                    self.frame_cache.clear();
+                },
+                SimulationCommand::GetCached => {
+                    let res = SimulationResponse::Cached(self.frame_cache.len());
+                    self.interface_tx.ez_send(res);
+
+                    let res = SimulationResponse::CacheUsage { used: self.frame_cache.memory_usage(), budget: self.frame_cache.budget() };
+                    self.interface_tx.ez_send(res);
+                },
+                SimulationCommand::SetCacheBudget(bytes) => {
+                    self.frame_cache.set_budget(bytes);
+                },
+                SimulationCommand::SnapshotCache(mut tx) => {
+                    tx.ez_send(self.frame_cache.snapshot());
                 }
-            } else {
-                break;
+                #[allow(unreachable_patterns)]
+                _ => log::warn!("Unhandled simulation command !")
             }
         }
 
         if let Some(f) = self.requested_frame {
-            if self.frame_cache.len() as u32 <= f {
+            if self.frame_cache.len() <= f {
                 let start = instant::Instant::now();
 
                 let mut now = start;
-                while now.duration_since(start).as_millis() < 100 && self.frame_cache.len() as u32 <= f {
+                while now.duration_since(start).as_millis() < 100 && self.frame_cache.len() <= f {
                     self.run_frame();
                     now = instant::Instant::now();
                 }
             } else {
                 let frame = self.get_frame(f).clone();
-                let res = SimulationResponse::Frame(f, frame);
+                let res = SimulationResponse::Frame(f, Box::new(frame));
 
                 self.requested_frame = None;
 
@@ -222,87 +561,418 @@ impl SimulationManager {
     }
 }
 
+impl Default for SimulationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SimulationState {
+    /// Scene tracks that only change how frames are viewed, see [`SimulationState::viewport`].
+    const VIEW_TRACKS: [&str; 3] = ["camera_x", "camera_y", "camera_zoom"];
+
     pub fn new() -> Self {
         Self {
             particles: vec![],
             constraints: vec![],
+            links: vec![],
             trigger_managers: vec![],
             gravity_accel: glam::Vec2::ZERO,
-            particle_collisions: false
+            time: 0.0,
+            tracks: animation::Tracks::new(),
+            camera: rendering::Camera::default(),
+            particle_collisions: false,
+            collision_broadphase: CollisionBroadphase::SpatialHash,
+            substeps: 1,
+            constraint_iterations: 1,
+            contacts: vec![],
+            previous_contacts: vec![],
+            next_particle_id: 0,
+            next_constraint_id: 0,
+            next_trigger_id: 0
         }
     }
 
-    pub fn add_particle(&mut self, particle: Particle) {
+    /// Rough number of bytes this state occupies, for budgeting the frame cache.
+    /// Heap data owned by constraints, links and events beyond their own size isn't counted.
+    pub fn estimated_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.particles.capacity() * std::mem::size_of::<Particle>()
+            + self.constraints.iter().map(|c| std::mem::size_of_val(&**c) + std::mem::size_of_val(c)).sum::<usize>()
+            + self.links.iter().map(|l| std::mem::size_of_val(&**l) + std::mem::size_of_val(l)).sum::<usize>()
+            + self.trigger_managers.iter().map(|m| m.estimated_size()).sum::<usize>()
+            + (self.contacts.capacity() + self.previous_contacts.capacity()) * std::mem::size_of::<Contact>()
+    }
+
+    pub fn add_particle(&mut self, mut particle: Particle) -> ParticleId {
+        particle.id = self.next_particle_id;
+        self.next_particle_id += 1;
+
         self.particles.push(particle);
+        self.next_particle_id - 1
+    }
+
+    pub fn add_link(&mut self, link: impl LinkConstraint + 'static) {
+        self.links.push(Box::new(link));
+    }
+
+    /// Adds `segments + 1` copies of `particle` evenly spaced from `start` to `end`, joined by ropes.
+    /// Returns the ids of the new particles in order.
+    pub fn add_chain(&mut self, start: glam::Vec2, end: glam::Vec2, segments: u32, particle: Particle) -> Vec<ParticleId> {
+        let segments = segments.max(1);
+        let segment_length = start.distance(end) / segments as f32;
+
+        let ids: Vec<ParticleId> = (0..=segments).map(|i| {
+            let position = start.lerp(end, i as f32 / segments as f32);
+            self.add_particle(Particle { position, last_position: position, ..particle.clone() })
+        }).collect();
+
+        for pair in ids.windows(2) {
+            self.add_link(links::RopeLink::new(pair[0], pair[1], segment_length));
+        }
+
+        ids
+    }
+
+    pub fn particle_index(&self, id: ParticleId) -> Option<usize> {
+        self.particles.iter().position(|p| p.id == id)
+    }
+
+    pub fn particle_index_map(&self) -> std::collections::HashMap<ParticleId, usize> {
+        self.particles.iter().enumerate().map(|(i, p)| (p.id, i)).collect()
+    }
+
+    /// The particle drawn on top at `point`, counting `margin` extra sim units around each one so small particles can be hit.
+    pub fn particle_at(&self, point: glam::Vec2, margin: f32) -> Option<ParticleId> {
+        self.particles.iter().rev()
+            .find(|p| p.position.distance(point) <= p.radius + margin)
+            .map(|p| p.id)
+    }
+
+    /// Earliest simulation time at which `self` can behave differently from `previous`, or `None` if nothing changed.
+    /// Edited keyframes only take effect from the key before them, so those are checked track by track.
+    /// Any other change counts from the start.
+    /// The camera offset and zoom only affect how frames are viewed, so changes to them are ignored.
+    /// With events that change the scene definition, later frames can't be updated with [`SimulationState::apply_scene`],
+    /// so any change counts from the start.
+    pub fn first_change_time(&self, previous: &SimulationState) -> Option<f32> {
+        let mut previous = previous.clone();
+        previous.copy_view(self);
+
+        let time = match (serde_json::to_value(&previous), serde_json::to_value(self)) {
+            (Ok(old), Ok(new)) => first_change_in(&old, &new)?,
+            _ => 0.0
+        };
+
+        let changes_scene = |state: &SimulationState| state.trigger_managers.iter().any(|m| m.changes_scene());
+        Some(if changes_scene(self) || changes_scene(&previous) { 0.0 } else { time })
+    }
+
+    /// Takes over the camera offset and zoom of `other` along with their keyframes.
+    pub fn copy_view(&mut self, other: &SimulationState) {
+        self.camera.offset = other.camera.offset;
+        self.camera.zoom = other.camera.zoom;
+
+        for name in Self::VIEW_TRACKS {
+            *self.tracks.get_mut(name) = other.tracks.get(name).cloned().unwrap_or_default();
+        }
+        self.tracks.cleanup();
     }
-    pub fn add_constraint(&mut self, constraint: impl Constraint + 'static) {
-        self.constraints.push(Box::new(constraint));
+
+    /// How to draw this frame: centered on where its camera follows, with the offset and zoom of `scene` at this frame's time.
+    /// Those are read from the scene rather than the frame so changing them doesn't need the frames to be redone.
+    pub fn viewport(&self, scene: &SimulationState) -> rendering::Viewport {
+        let mut camera = self.camera.clone();
+        camera.copy_settings(&scene.camera);
+
+        scene.tracks.apply(self.time, [
+            ("camera_x", &mut camera.offset.x),
+            ("camera_y", &mut camera.offset.y),
+            ("camera_zoom", &mut camera.zoom)
+        ]);
+
+        camera.viewport()
+    }
+
+    /// Replaces the scene definition (constraints, links, triggers, keyframes and settings) with the one from `scene`,
+    /// keeping the particles, time and how often each trigger has fired. Used to carry an edit over to a frame that it doesn't affect yet.
+    pub fn apply_scene(&mut self, scene: &SimulationState) {
+        self.constraints = scene.constraints.clone();
+        self.next_constraint_id = scene.next_constraint_id;
+        self.links = scene.links.clone();
+        let previous = std::mem::replace(&mut self.trigger_managers, scene.trigger_managers.clone());
+        for manager in &mut self.trigger_managers {
+            if let Some(previous) = previous.iter().find(|p| p.id() == manager.id()) {
+                manager.keep_firing_state(previous);
+            }
+        }
+        self.next_trigger_id = scene.next_trigger_id;
+        self.gravity_accel = scene.gravity_accel;
+        self.tracks = scene.tracks.clone();
+        self.camera.copy_settings(&scene.camera);
+        self.particle_collisions = scene.particle_collisions;
+        self.collision_broadphase = scene.collision_broadphase;
+        self.substeps = scene.substeps;
+        self.constraint_iterations = scene.constraint_iterations;
+    }
+
+    pub fn add_constraint(&mut self, constraint: impl Constraint + 'static) -> ConstraintId {
+        self.add_boxed_constraint(Box::new(constraint))
+    }
+
+    pub fn add_boxed_constraint(&mut self, constraint: Box<dyn Constraint>) -> ConstraintId {
+        let id = self.next_constraint_id;
+        self.next_constraint_id += 1;
+
+        self.constraints.push(SceneConstraint { id, constraint });
+        id
     }
 
-    pub fn add_trigger_manager(&mut self, manager: event::TriggerManager) {
+    pub fn constraint_index(&self, id: ConstraintId) -> Option<usize> {
+        self.constraints.iter().position(|c| c.id == id)
+    }
+
+    pub fn add_trigger_manager(&mut self, mut manager: event::TriggerManager) {
+        manager.set_id(self.next_trigger_id);
+        self.next_trigger_id += 1;
+
         self.trigger_managers.push(manager);
     }
 
-    fn solve_particle_collisions(&mut self) {
+    fn resolve_particle_pair(&mut self, i: usize, j: usize) {
+        let Some((left, right)) = particle_pair_mut(&mut self.particles, i, j) else { return; };
+        let (a, b) = (left.id.min(right.id), left.id.max(right.id));
+
+        let diff_centers = left.position - right.position;
+        let dst_centers_sq = diff_centers.length_squared();
+        let sum_radii_sq = (left.radius + right.radius) * (left.radius + right.radius);
+        
+        let inv_mass_sum = left.inverse_mass() + right.inverse_mass();
+        
+        if dst_centers_sq < sum_radii_sq && inv_mass_sum > 0.0 {
+            let dst_centers = dst_centers_sq.sqrt();
+            let push_dst = (left.radius + right.radius - dst_centers).max(0.0);
+            // Particles spawned on top of each other are pushed apart along an arbitrary but fixed axis
+            let normal = if dst_centers > 0.0 { diff_centers / dst_centers } else { glam::Vec2::X };
+            let push_vec = normal * push_dst;
+
+            // Lighter particles take more of the push
+            left.position += push_vec * (left.inverse_mass() / inv_mass_sum);
+            right.position -= push_vec * (right.inverse_mass() / inv_mass_sum);
+
+            self.contacts.push(Contact::Particles { a, b });
+        }
+    }
+
+    fn solve_particle_collisions_brute_force(&mut self) {
         let len = self.particles.len();
         for i in 0..len {
             for j in (i + 1)..len {
-                let (left, right) = {
-                    let (head, tail) = self.particles.split_at_mut(j);
-                    (&mut head[i], &mut tail[0])
-                };
-
-                let diff_centers = left.position - right.position;
-                let dst_centers_sq = diff_centers.length_squared();
-                let sum_radii_sq = (left.radius + right.radius) * (left.radius + right.radius);
-                
-                if dst_centers_sq < sum_radii_sq {
-                    let dst_centers = dst_centers_sq.sqrt();
-                    let push_dst = (left.radius + right.radius - dst_centers).max(0.0);
-                    let push_vec = (diff_centers / dst_centers) * push_dst;
-
-                    // Apply half of the push to each (optional, more realistic)
-                    left.position += push_vec * 0.5;
-                    right.position -= push_vec * 0.5;
+                self.resolve_particle_pair(i, j);
+            }
+        }
+    }
+
+    /// Pairs are resolved in the same (i, j) order as the brute-force path, and the grid follows particles as
+    /// they are pushed, so both paths give identical results.
+    fn solve_particle_collisions_spatial_hash(&mut self) {
+        const CELL_MARGIN: f32 = 1.5;
+
+        let max_radius = self.particles.iter().map(|p| p.radius).fold(0.0f32, f32::max);
+        if max_radius <= 0.0 || !max_radius.is_finite() {
+            return;
+        }
+
+        let cell_size = 2.0 * max_radius * CELL_MARGIN;
+        // How far a particle can move from where its neighbors were looked up before it could touch one not found there
+        let slack = cell_size - 2.0 * max_radius;
+        let cell_of = |p: glam::Vec2| ((p.x / cell_size).floor() as i32, (p.y / cell_size).floor() as i32);
+
+        let mut cells: Vec<(i32, i32)> = self.particles.iter().map(|p| cell_of(p.position)).collect();
+        let mut grid: std::collections::HashMap<(i32, i32), Vec<usize>> = std::collections::HashMap::new();
+        for (i, &cell) in cells.iter().enumerate() {
+            grid.entry(cell).or_default().push(i);
+        }
+
+        // Particles after `after` in the 3x3 cells around `position`, in index order
+        let neighbors = |grid: &std::collections::HashMap<(i32, i32), Vec<usize>>, position: glam::Vec2, after: usize| {
+            let (cx, cy) = cell_of(position);
+            let mut found = vec![];
+
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if let Some(cell) = grid.get(&(cx.saturating_add(dx), cy.saturating_add(dy))) {
+                        found.extend(cell.iter().copied().filter(|&j| j > after));
+                    }
+                }
+            }
+
+            found.sort_unstable();
+            found.dedup();
+            found
+        };
+
+        for i in 0..self.particles.len() {
+            let mut origin = self.particles[i].position;
+            let mut candidates = neighbors(&grid, origin, i);
+            let mut next = 0;
+
+            while let Some(&j) = candidates.get(next) {
+                next += 1;
+                self.resolve_particle_pair(i, j);
+
+                for k in [i, j] {
+                    let cell = cell_of(self.particles[k].position);
+                    if cell != cells[k] {
+                        if let Some(old) = grid.get_mut(&cells[k]) {
+                            old.retain(|&other| other != k);
+                        }
+                        grid.entry(cell).or_default().push(k);
+                        cells[k] = cell;
+                    }
+                }
+
+                // Particles after j haven't moved since they were looked up, only i has
+                if self.particles[i].position.distance(origin) > slack {
+                    origin = self.particles[i].position;
+                    candidates = neighbors(&grid, origin, j);
+                    next = 0;
                 }
             }
         }
     }
 
+    fn solve_particle_collisions(&mut self) {
+        match self.collision_broadphase {
+            CollisionBroadphase::BruteForce => self.solve_particle_collisions_brute_force(),
+            CollisionBroadphase::SpatialHash => self.solve_particle_collisions_spatial_hash()
+        }
+    }
+
     fn solve_constraints(&mut self, steps: u32) {
+        let indices = self.particle_index_map();
+
         for _ in 0..steps {
             for constraint in &self.constraints {
-                for particle in &mut self.particles {
-                    constraint.constrain(particle);
+                // Pinned particles stay where they are placed, even against a wall
+                for particle in self.particles.iter_mut().filter(|p| p.inverse_mass() > 0.0) {
+                    if constraint.constrain(particle, self.time) {
+                        self.contacts.push(Contact::Constraint { constraint: constraint.id, particle: particle.id });
+                    }
                 }
             }
+
+            for link in &self.links {
+                let (a, b) = link.particles();
+                if let (Some(&i), Some(&j)) = (indices.get(&a), indices.get(&b))
+                    && let Some((a, b)) = particle_pair_mut(&mut self.particles, i, j) {
+                    link.constrain(a, b);
+                }
+            }
+        }
+    }
+
+    fn apply_link_forces(&mut self, dt: f32) {
+        let indices = self.particle_index_map();
+
+        for link in &self.links {
+            let (a, b) = link.particles();
+            if let (Some(&i), Some(&j)) = (indices.get(&a), indices.get(&b))
+                && let Some((a, b)) = particle_pair_mut(&mut self.particles, i, j) {
+                link.apply_forces(a, b, dt);
+            }
         }
     }
 
+    /// Whether `contact` from [`SimulationState::contacts`] began in the last substep rather than continuing from the one before.
+    pub fn is_new_contact(&self, contact: &Contact) -> bool {
+        self.previous_contacts.binary_search(contact).is_err()
+    }
+
     fn solve_pbd(&mut self, dt: f32) {
+        std::mem::swap(&mut self.contacts, &mut self.previous_contacts);
+        self.contacts.clear();
+        self.apply_link_forces(dt);
+
         for particle in &mut self.particles {
-            let v = (particle.position - particle.last_position) / dt + dt * self.gravity_accel;
+            particle.velocity_overridden = false;
+
+            if particle.inverse_mass() == 0.0 {
+                particle.velocity = glam::Vec2::ZERO;
+                particle.last_position = particle.position;
+                continue;
+            }
+
+            particle.velocity += dt * self.gravity_accel;
 
             particle.last_position = particle.position;
-            particle.position += dt * v;
+            particle.position += dt * particle.velocity;
         }
 
-        self.solve_constraints(1);
+        self.solve_constraints(self.constraint_iterations);
         if self.particle_collisions { self.solve_particle_collisions(); }
+
+        // Constraint iterations can resolve the same contact several times
+        self.contacts.sort_unstable();
+        self.contacts.dedup();
+
+        for particle in &mut self.particles {
+            if !particle.velocity_overridden {
+                particle.velocity = (particle.position - particle.last_position) / dt;
+            }
+        }
     }
 
-    fn update_triggers(&mut self) {
-        let tms: Vec<event::TriggerManager> = self.trigger_managers.drain(..).collect();
-        for tm in &tms {
-            tm.process(self);
+    fn animate(&mut self) {
+        let time = self.time;
+
+        self.tracks.apply(time, [
+            ("gravity_x", &mut self.gravity_accel.x),
+            ("gravity_y", &mut self.gravity_accel.y)
+        ]);
+
+        for constraint in &mut self.constraints {
+            constraint.animate(time);
+        }
+
+        for manager in &mut self.trigger_managers {
+            manager.animate(time);
+        }
+    }
+
+    /// Times of every keyframe in the scene, sorted and deduplicated.
+    pub fn key_times(&self) -> Vec<f32> {
+        let mut times: Vec<f32> = self.tracks.key_times()
+            .chain(self.constraints.iter().filter_map(|c| c.tracks()).flat_map(|t| t.key_times()))
+            .chain(self.trigger_managers.iter().flat_map(|m| m.key_times()))
+            .collect();
+
+        times.sort_by(f32::total_cmp);
+        times.dedup_by(|a, b| (*a - *b).abs() < animation::Track::TIME_EPSILON);
+        times
+    }
+
+    fn update_triggers(&mut self, dt: f32) {
+        let mut tms: Vec<event::TriggerManager> = self.trigger_managers.drain(..).collect();
+        for tm in &mut tms {
+            tm.process(self, dt);
         }
         self.trigger_managers = tms;
     }
 
     fn step(&mut self, dt: f32) {
-        self.update_triggers();
+        self.animate();
+        self.update_triggers(dt);
+        self.time += dt;
         self.solve_pbd(dt);
+        self.camera.update(&self.particles, dt);
+    }
+
+    /// The state one frame later at the given frame rate, using the scene's substep setting.
+    pub fn next_frame(&self, fps: f32) -> SimulationState {
+        let mut next = self.clone();
+        next.multi_step(next.substeps, 1.0 / fps);
+        next
     }
 
     pub fn single_step(&mut self, dt: f32) {
@@ -310,6 +980,7 @@ impl SimulationState {
     }
 
     pub fn multi_step(&mut self, steps: u32, dt: f32) {
+        let steps = steps.max(1);
         for _ in 0..steps {
             self.step(dt / steps as f32);
         }
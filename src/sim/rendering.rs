@@ -1,5 +1,8 @@
+#[derive(Clone, Copy, Debug)]
 pub struct Viewport {
-    pub sim_units_per_vw: f32
+    pub sim_units_per_vw: f32,
+    /// Point in sim units shown at the center of the view.
+    pub center: glam::Vec2
 }
 
 impl Viewport {
@@ -7,14 +10,152 @@ impl Viewport {
         let logical_points_per_sim_unit = vw_points / self.sim_units_per_vw;
         sim_units * logical_points_per_sim_unit
     }
+
+    pub fn logical_points_to_sim_units(&self, points: f32, vw_points: f32) -> f32 {
+        points * self.sim_units_per_vw / vw_points
+    }
+
+    /// Position of a point in sim units within a view of the given size, relative to its top left corner.
+    pub fn sim_to_view(&self, p: glam::Vec2, size: glam::Vec2) -> glam::Vec2 {
+        size * 0.5 + (p - self.center) * (size.x / self.sim_units_per_vw)
+    }
+
+    /// Inverse of [`Viewport::sim_to_view`].
+    pub fn view_to_sim(&self, pos: glam::Vec2, size: glam::Vec2) -> glam::Vec2 {
+        self.center + (pos - size * 0.5) * (self.sim_units_per_vw / size.x)
+    }
+
+    /// Screen position of a point in sim units, for a view filling `rect`.
+    pub fn sim_to_screen(&self, p: glam::Vec2, rect: egui::Rect) -> egui::Pos2 {
+        let pos = self.sim_to_view(p, glam::vec2(rect.width(), rect.height()));
+        rect.min + egui::vec2(pos.x, pos.y)
+    }
+
+    /// Point in sim units under a screen position. Inverse of [`Viewport::sim_to_screen`].
+    pub fn screen_to_sim(&self, pos: egui::Pos2, rect: egui::Rect) -> glam::Vec2 {
+        let offset = pos - rect.min;
+        self.view_to_sim(glam::vec2(offset.x, offset.y), glam::vec2(rect.width(), rect.height()))
+    }
 }
 
-pub trait SimRenderer {
-    fn render(&self, sim: &super::SimulationState, ui: &mut egui::Ui);
+impl Default for Viewport {
+    fn default() -> Self {
+        Self { sim_units_per_vw: Camera::VIEW_WIDTH, center: glam::Vec2::ZERO }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum CameraFollow {
+    Fixed,
+    Particle(super::ParticleId),
+    CenterOfMass
+}
+
+impl CameraFollow {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraFollow::Fixed => "Fixed",
+            CameraFollow::Particle(_) => "Particle",
+            CameraFollow::CenterOfMass => "Center of mass"
+        }
+    }
+}
+
+/// The scene's camera. It looks at the followed point plus `offset` and shows `VIEW_WIDTH / zoom` sim units across.
+/// Only following is simulated, `offset` and `zoom` are applied when a frame is drawn (see
+/// [`super::SimulationState::viewport`]) and can be keyframed through the scene tracks `camera_x`, `camera_y` and `camera_zoom`.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Camera {
+    pub offset: glam::Vec2,
+    pub zoom: f32,
+    pub follow: CameraFollow,
+    /// Time in seconds the view takes to catch up with a moving target, roughly. 0 follows it exactly.
+    pub smoothing: f32,
+
+    /// Current smoothed position of the followed point.
+    position: glam::Vec2
+}
+
+impl Camera {
+    /// Sim units across the view at zoom 1.
+    pub const VIEW_WIDTH: f32 = 2.0;
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport { sim_units_per_vw: Self::VIEW_WIDTH / self.zoom.max(1e-3), center: self.position + self.offset }
+    }
+
+    pub fn position(&self) -> glam::Vec2 {
+        self.position
+    }
+
+    /// The point to follow, or `None` if the followed particle doesn't exist.
+    fn target(&self, particles: &[super::Particle]) -> Option<glam::Vec2> {
+        let followed = match self.follow {
+            CameraFollow::Fixed => glam::Vec2::ZERO,
+            CameraFollow::Particle(id) => particles.iter().find(|p| p.id() == id)?.position,
+            CameraFollow::CenterOfMass => {
+                let (weighted, mass) = particles.iter()
+                    .filter(|p| p.mass > 0.0)
+                    .fold((glam::Vec2::ZERO, 0.0), |(w, m), p| (w + p.position * p.mass, m + p.mass));
+
+                if mass <= 0.0 {
+                    return None;
+                }
+                weighted / mass
+            }
+        };
+
+        Some(followed)
+    }
+
+    /// Moves onto the followed point right away, e.g. after the camera settings changed.
+    pub fn snap(&mut self, particles: &[super::Particle]) {
+        if let Some(target) = self.target(particles) {
+            self.position = target;
+        }
+    }
+
+    /// Moves towards the followed point over `dt` seconds.
+    pub fn update(&mut self, particles: &[super::Particle], dt: f32) {
+        let Some(target) = self.target(particles) else { return; };
 
-    fn line_segment(&self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: egui::Color32, ui: &mut egui::Ui, render_state: &RenderState);
-    fn circle(&self, center: glam::Vec2, radius: f32, thickness: f32, color: egui::Color32, ui: &mut egui::Ui, render_state: &RenderState);
-    fn circle_filled(&self, center: glam::Vec2, radius: f32, color: egui::Color32, ui: &mut egui::Ui, render_state: &RenderState);
+        if self.follow == CameraFollow::Fixed || self.smoothing <= 0.0 {
+            self.position = target;
+        } else {
+            self.position += (target - self.position) * (1.0 - (-dt / self.smoothing).exp());
+        }
+    }
+
+    /// Zooms by `factor` while keeping `anchor` in place in a view currently showing `viewport`.
+    pub fn zoom_at(&mut self, factor: f32, anchor: glam::Vec2, viewport: &Viewport) {
+        self.zoom *= factor;
+        self.offset += (anchor - viewport.center) * (1.0 - 1.0 / factor);
+    }
+
+    /// Takes over the settings of `other` but keeps the current position of the followed point.
+    pub fn copy_settings(&mut self, other: &Camera) {
+        *self = Camera { position: self.position, ..other.clone() };
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: glam::Vec2::ZERO,
+            zoom: 1.0,
+            follow: CameraFollow::Fixed,
+            smoothing: 0.5,
+            position: glam::Vec2::ZERO
+        }
+    }
+}
+
+/// Drawing target for primitives given in sim units.
+pub trait SimRenderer {
+    fn line_segment(&mut self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: egui::Color32);
+    fn circle(&mut self, center: glam::Vec2, radius: f32, thickness: f32, color: egui::Color32);
+    fn circle_filled(&mut self, center: glam::Vec2, radius: f32, color: egui::Color32);
 }
 
 pub trait RenderableTool {
@@ -22,10 +163,38 @@ pub trait RenderableTool {
 }
 
 pub struct RenderState {
-    center: egui::Pos2,
-    vw: f32
+    time: f32
 }
 
+impl RenderState {
+    /// Simulation time in seconds of the frame being drawn.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+}
+
+/// Draws the particles, constraints and links of `sim` with any renderer.
+pub fn draw_simulation(sim: &super::SimulationState, renderer: &mut dyn SimRenderer) {
+    let render_state = RenderState { time: sim.time };
+
+    for particle in &sim.particles {
+        renderer.circle_filled(particle.position, particle.radius, particle.color);
+    }
+
+    for constraint in &sim.constraints {
+        constraint.draw_sim(renderer, &render_state);
+    }
+
+    let indices = sim.particle_index_map();
+    for link in &sim.links {
+        let (a, b) = link.particles();
+        if let (Some(&a), Some(&b)) = (indices.get(&a), indices.get(&b)) {
+            link.draw_sim(&sim.particles[a], &sim.particles[b], renderer, &render_state);
+        }
+    }
+}
+
+/// Renders into the preview panel with the egui painter.
 pub struct CpuSimRenderer {
     pub viewport: Viewport,
 }
@@ -33,73 +202,269 @@ pub struct CpuSimRenderer {
 impl CpuSimRenderer {
     pub fn new() -> Self {
         Self {
-            viewport: Viewport { sim_units_per_vw: 2.0 }
+            viewport: Viewport::default()
         }
     }
-}
 
-impl SimRenderer for CpuSimRenderer {
-    fn render(&self, sim: &super::SimulationState, ui: &mut egui::Ui) {
+    /// Fills the available space with the simulation. The returned response senses clicks and drags on it.
+    pub fn render(&self, sim: &super::SimulationState, ui: &mut egui::Ui) -> egui::Response {
         let vw = ui.available_width();
         let vh = ui.available_height();
 
-        let (rect, _) = ui.allocate_exact_size(egui::vec2(vw, vh), egui::Sense::empty());
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(vw, vh), egui::Sense::click_and_drag());
+
+        let mut target = PainterTarget {
+            painter: ui.painter(),
+            viewport: &self.viewport,
+            rect
+        };
+
+        draw_simulation(sim, &mut target);
+
+        response
+    }
+}
+
+impl Default for CpuSimRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct PainterTarget<'a> {
+    painter: &'a egui::Painter,
+    viewport: &'a Viewport,
+    rect: egui::Rect
+}
+
+impl PainterTarget<'_> {
+    fn to_screen(&self, p: glam::Vec2) -> egui::Pos2 {
+        self.viewport.sim_to_screen(p, self.rect)
+    }
+
+    fn to_points(&self, sim_units: f32) -> f32 {
+        self.viewport.sim_units_to_logical_points(sim_units, self.rect.width())
+    }
+}
+
+impl SimRenderer for PainterTarget<'_> {
+    fn line_segment(&mut self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: egui::Color32) {
+        self.painter.line_segment([self.to_screen(a), self.to_screen(b)], egui::Stroke::new(self.to_points(thickness), color));
+    }
+
+    fn circle(&mut self, center: glam::Vec2, radius: f32, thickness: f32, color: egui::Color32) {
+        self.painter.circle_stroke(self.to_screen(center), self.to_points(radius), egui::Stroke::new(self.to_points(thickness), color));
+    }
+
+    fn circle_filled(&mut self, center: glam::Vec2, radius: f32, color: egui::Color32) {
+        self.painter.circle_filled(self.to_screen(center), self.to_points(radius), color);
+    }
+}
+
+/// Software rasterizer that draws into an RGBA image without a window or GPU.
+/// Shapes are antialiased over one pixel and alpha blended in premultiplied space.
+pub struct ImageSimRenderer {
+    pub viewport: Viewport,
+    image: egui::ColorImage,
+}
+
+impl ImageSimRenderer {
+    pub fn new(width: usize, height: usize, background: egui::Color32) -> Self {
+        Self {
+            viewport: Viewport::default(),
+            image: egui::ColorImage::new([width, height], background)
+        }
+    }
+
+    pub fn image(&self) -> &egui::ColorImage {
+        &self.image
+    }
+
+    pub fn into_image(self) -> egui::ColorImage {
+        self.image
+    }
+
+    pub fn clear(&mut self, background: egui::Color32) {
+        self.image.pixels.fill(background);
+    }
+
+    /// Clears the image and draws `sim` into it.
+    pub fn render(&mut self, sim: &super::SimulationState, background: egui::Color32) -> &egui::ColorImage {
+        self.clear(background);
+        draw_simulation(sim, self);
+        &self.image
+    }
+
+    fn to_pixels(&self, sim_units: f32) -> f32 {
+        self.viewport.sim_units_to_logical_points(sim_units, self.image.width() as f32)
+    }
+
+    fn to_image(&self, p: glam::Vec2) -> glam::Vec2 {
+        self.viewport.sim_to_view(p, glam::vec2(self.image.width() as f32, self.image.height() as f32))
+    }
+
+    /// Blends `color` into the pixels between `min` and `max` (plus a pixel of margin),
+    /// with coverage given by the signed `distance` from each pixel center to the shape edge.
+    fn fill_shape(&mut self, min: glam::Vec2, max: glam::Vec2, color: egui::Color32, distance: impl Fn(glam::Vec2) -> f32) {
+        let [width, height] = self.image.size;
+
+        let x0 = (min.x.floor() - 1.0).max(0.0) as usize;
+        let y0 = (min.y.floor() - 1.0).max(0.0) as usize;
+        let x1 = ((max.x.ceil() + 1.0).max(0.0) as usize).min(width);
+        let y1 = ((max.y.ceil() + 1.0).max(0.0) as usize).min(height);
 
-        let render_state = RenderState { center: rect.center(), vw };
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let coverage = (0.5 - distance(glam::vec2(x as f32 + 0.5, y as f32 + 0.5))).clamp(0.0, 1.0);
 
-        for particle in &sim.particles {
-            let mut pos = egui::pos2(particle.position.x, particle.position.y);
-            pos.x = self.viewport.sim_units_to_logical_points(pos.x, vw) + rect.center().x;
-            pos.y = self.viewport.sim_units_to_logical_points(pos.y, vw) + rect.center().y;
+                if coverage > 0.0 {
+                    let pixel = &mut self.image.pixels[y * width + x];
+                    *pixel = blend(*pixel, color.gamma_multiply(coverage));
+                }
+            }
+        }
+    }
+}
+
+fn blend(dst: egui::Color32, src: egui::Color32) -> egui::Color32 {
+    let inv_alpha = 255 - src.a() as u32;
+    let mix = |s: u8, d: u8| (s as u32 + (d as u32 * inv_alpha + 127) / 255).min(255) as u8;
+
+    egui::Color32::from_rgba_premultiplied(mix(src.r(), dst.r()), mix(src.g(), dst.g()), mix(src.b(), dst.b()), mix(src.a(), dst.a()))
+}
+
+impl SimRenderer for ImageSimRenderer {
+    fn line_segment(&mut self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: egui::Color32) {
+        let (a, b) = (self.to_image(a), self.to_image(b));
+        let half_width = (self.to_pixels(thickness) * 0.5).max(0.5);
+
+        let ab = b - a;
+        let len_sq = ab.length_squared();
+
+        self.fill_shape(a.min(b) - half_width, a.max(b) + half_width, color, |p| {
+            let t = if len_sq > 0.0 { ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0) } else { 0.0 };
+            p.distance(a + ab * t) - half_width
+        });
+    }
+
+    fn circle(&mut self, center: glam::Vec2, radius: f32, thickness: f32, color: egui::Color32) {
+        let center = self.to_image(center);
+        let radius = self.to_pixels(radius);
+        let half_width = (self.to_pixels(thickness) * 0.5).max(0.5);
 
-            let radius = self.viewport.sim_units_to_logical_points(particle.radius, vw);
+        let extent = glam::Vec2::splat(radius + half_width);
+        self.fill_shape(center - extent, center + extent, color, |p| (p.distance(center) - radius).abs() - half_width);
+    }
 
-            ui.painter().circle_filled(pos, radius, particle.color);
+    fn circle_filled(&mut self, center: glam::Vec2, radius: f32, color: egui::Color32) {
+        let center = self.to_image(center);
+        let radius = self.to_pixels(radius);
+
+        let extent = glam::Vec2::splat(radius);
+        self.fill_shape(center - extent, center + extent, color, |p| p.distance(center) - radius);
+    }
+}
+
+/// Records primitives as SVG elements, in pixel units of a `width` by `height` canvas.
+pub struct SvgSimRenderer {
+    pub viewport: Viewport,
+    width: u32,
+    height: u32,
+    background: egui::Color32,
+    elements: Vec<String>
+}
+
+impl SvgSimRenderer {
+    pub fn new(width: u32, height: u32, background: egui::Color32) -> Self {
+        Self {
+            viewport: Viewport::default(),
+            width,
+            height,
+            background,
+            elements: Vec::new()
         }
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
 
-        for constraint in &sim.constraints {
-            constraint.draw_sim(self, ui, &render_state);
+    /// Clears the recorded elements, draws `sim` and returns the finished document.
+    pub fn render(&mut self, sim: &super::SimulationState) -> String {
+        self.clear();
+        draw_simulation(sim, self);
+        self.to_svg()
+    }
+
+    /// The recorded elements as a standalone SVG document.
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = self.width, h = self.height
+        );
+
+        if self.background.a() > 0 {
+            svg += &format!("<rect width=\"100%\" height=\"100%\" {}/>\n", svg_paint("fill", self.background));
         }
+
+        for element in &self.elements {
+            svg += element;
+            svg.push('\n');
+        }
+
+        svg += "</svg>\n";
+        svg
     }
 
-    fn line_segment(&self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: egui::Color32, ui: &mut egui::Ui, render_state: &RenderState) {
-        let vw = render_state.vw;
-        let c = render_state.center;
+    fn to_pixels(&self, sim_units: f32) -> f32 {
+        self.viewport.sim_units_to_logical_points(sim_units, self.width as f32)
+    }
 
-        let a_x = self.viewport.sim_units_to_logical_points(a.x, vw) + c.x;
-        let a_y = self.viewport.sim_units_to_logical_points(a.y, vw) + c.y;
-        
-        let b_x = self.viewport.sim_units_to_logical_points(b.x, vw) + c.x;
-        let b_y = self.viewport.sim_units_to_logical_points(b.y, vw) + c.y;
+    fn to_canvas(&self, p: glam::Vec2) -> glam::Vec2 {
+        self.viewport.sim_to_view(p, glam::vec2(self.width as f32, self.height as f32))
+    }
+}
 
-        let thickness = self.viewport.sim_units_to_logical_points(thickness, vw);
+/// `fill="..."` or `stroke="..."` attributes for a color, with an opacity attribute when it isn't opaque.
+fn svg_paint(attribute: &str, color: egui::Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut paint = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, r, g, b);
 
-        ui.painter().line_segment([egui::pos2(a_x, a_y), egui::pos2(b_x, b_y)], egui::Stroke::new(thickness, color));
+    if a < 255 {
+        paint += &format!(" {}-opacity=\"{:.3}\"", attribute, a as f32 / 255.0);
     }
 
-    fn circle(&self, center: glam::Vec2, radius: f32, thickness: f32, color: egui::Color32, ui: &mut egui::Ui, render_state: &RenderState) {
-        let vw = render_state.vw;
-        let c = render_state.center;
+    paint
+}
+
+impl SimRenderer for SvgSimRenderer {
+    fn line_segment(&mut self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: egui::Color32) {
+        let (a, b) = (self.to_canvas(a), self.to_canvas(b));
+        let width = self.to_pixels(thickness);
 
-        let c_x = self.viewport.sim_units_to_logical_points(center.x, vw) + c.x;
-        let c_y = self.viewport.sim_units_to_logical_points(center.y, vw) + c.y;
-        
-        let radius = self.viewport.sim_units_to_logical_points(radius, vw);
+        self.elements.push(format!(
+            "<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" {} stroke-width=\"{:.2}\" stroke-linecap=\"round\"/>",
+            a.x, a.y, b.x, b.y, svg_paint("stroke", color), width
+        ));
+    }
 
-        let thickness = self.viewport.sim_units_to_logical_points(thickness, vw);
+    fn circle(&mut self, center: glam::Vec2, radius: f32, thickness: f32, color: egui::Color32) {
+        let center = self.to_canvas(center);
+        let (radius, width) = (self.to_pixels(radius), self.to_pixels(thickness));
 
-        ui.painter().circle_stroke(egui::pos2(c_x, c_y), radius, egui::Stroke::new(thickness, color));
+        self.elements.push(format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"none\" {} stroke-width=\"{:.2}\"/>",
+            center.x, center.y, radius, svg_paint("stroke", color), width
+        ));
     }
 
-    fn circle_filled(&self, center: glam::Vec2, radius: f32, color: egui::Color32, ui: &mut egui::Ui, render_state: &RenderState) {
-        let vw = render_state.vw;
-        let c = render_state.center;
-        
-        let c_x = self.viewport.sim_units_to_logical_points(center.x, vw) + c.x;
-        let c_y = self.viewport.sim_units_to_logical_points(center.y, vw) + c.y;
-        
-        let radius = self.viewport.sim_units_to_logical_points(radius, vw);
+    fn circle_filled(&mut self, center: glam::Vec2, radius: f32, color: egui::Color32) {
+        let center = self.to_canvas(center);
+        let radius = self.to_pixels(radius);
 
-        ui.painter().circle_filled(egui::pos2(c_x, c_y), radius, color);  
+        self.elements.push(format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>",
+            center.x, center.y, radius, svg_paint("fill", color)
+        ));
     }
 }
@@ -0,0 +1,96 @@
+use anyhow::Context;
+
+/// Version written by [`save_scene`]. Bump this and append to [`MIGRATIONS`] whenever the file layout changes
+/// in a way `#[serde(default)]` can't cover.
+pub const CURRENT_VERSION: u32 = 3;
+
+/// `MIGRATIONS[i]` upgrades the `scene` of a version `i + 1` file to version `i + 2`.
+const MIGRATIONS: &[fn(&mut serde_json::Value)] = &[number_constraints, number_trigger_managers];
+
+/// Version 2 gives constraints ids, numbered in the order they were listed.
+fn number_constraints(scene: &mut serde_json::Value) {
+    let mut count = 0u64;
+
+    if let Some(constraints) = scene.get_mut("constraints").and_then(|c| c.as_array_mut()) {
+        for constraint in constraints.iter_mut() {
+            *constraint = serde_json::json!({ "id": count, "constraint": constraint.take() });
+            count += 1;
+        }
+    }
+
+    if let Some(scene) = scene.as_object_mut() {
+        scene.insert("next_constraint_id".to_string(), count.into());
+    }
+}
+
+/// Version 3 gives trigger managers ids, which older files lack.
+fn number_trigger_managers(scene: &mut serde_json::Value) {
+    let mut count = 0u64;
+
+    if let Some(managers) = scene.get_mut("trigger_managers").and_then(|m| m.as_array_mut()) {
+        for manager in managers.iter_mut().filter_map(|m| m.as_object_mut()) {
+            manager.insert("id".to_string(), count.into());
+            count += 1;
+        }
+    }
+
+    if let Some(scene) = scene.as_object_mut() {
+        scene.insert("next_trigger_id".to_string(), count.into());
+    }
+}
+const _: () = assert!(MIGRATIONS.len() as u32 + 1 == CURRENT_VERSION);
+
+#[derive(serde::Serialize)]
+struct SceneFileRef<'a> {
+    version: u32,
+    scene: &'a super::SimulationState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    export: Option<&'a crate::export::ExportSettings>
+}
+
+#[derive(serde::Deserialize)]
+struct SceneFile {
+    version: u32,
+    scene: serde_json::Value,
+    #[serde(default)]
+    export: Option<crate::export::ExportSettings>
+}
+
+/// The contents of a scene file.
+pub struct Scene {
+    pub state: super::SimulationState,
+    /// Export settings the scene was last saved with, if any.
+    pub export: Option<crate::export::ExportSettings>
+}
+
+pub fn save_scene(state: &super::SimulationState, export: Option<&crate::export::ExportSettings>) -> anyhow::Result<String> {
+    let file = SceneFileRef { version: CURRENT_VERSION, scene: state, export };
+
+    serde_json::to_string_pretty(&file).context("Failed to serialize scene")
+}
+
+pub fn load_scene(data: &str) -> anyhow::Result<Scene> {
+    let SceneFile { version, mut scene, export } = serde_json::from_str(data).context("Not a valid scene file")?;
+
+    if version == 0 || version > CURRENT_VERSION {
+        anyhow::bail!("Unsupported scene file version {} (this build reads up to version {})", version, CURRENT_VERSION);
+    }
+
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(&mut scene);
+    }
+
+    Ok(Scene { state: serde_json::from_value(scene).context("Failed to load scene")?, export })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_scene_file(state: &super::SimulationState, export: Option<&crate::export::ExportSettings>, path: &std::path::Path) -> anyhow::Result<()> {
+    std::fs::write(path, save_scene(state, export)?).with_context(|| format!("Failed to write \"{}\"", path.display()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_scene_file(path: &std::path::Path) -> anyhow::Result<Scene> {
+    let data = std::fs::read_to_string(path).with_context(|| format!("Failed to read \"{}\"", path.display()))?;
+
+    load_scene(&data)
+}
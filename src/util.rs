@@ -14,6 +14,27 @@ pub fn show_error_dialog(message: &str) {
     log::error!("{}", message); // TODO: window.alert()
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn show_file_dialog(text: &str, mode: dialog::FileSelectionMode) -> Option<std::path::PathBuf> {
+    match dialog::FileSelection::new(text).title("simul8").mode(mode).show() {
+        Ok(path) => path.map(std::path::PathBuf::from),
+        Err(e) => {
+            show_error_dialog(&format!("Failed to display file dialog: \"{:?}\"", e));
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_open_path(text: &str) -> Option<std::path::PathBuf> {
+    show_file_dialog(text, dialog::FileSelectionMode::Open)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_save_path(text: &str) -> Option<std::path::PathBuf> {
+    show_file_dialog(text, dialog::FileSelectionMode::Save)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn spawn<F>(fut: F) where F: futures::Future<Output: Send> + Send + 'static{
     smol::spawn(fut).detach();
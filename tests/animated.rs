@@ -0,0 +1,35 @@
+use simul8::export::{FrameSink, animated::ApngSink};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("simul8-{}-{}.png", name, std::process::id()))
+}
+
+fn frame() -> egui::ColorImage {
+    egui::ColorImage::new([8, 4], egui::Color32::from_rgb(10, 200, 30))
+}
+
+#[test]
+fn finished_apng_is_kept() {
+    let path = temp_path("apng-finished");
+    let mut sink = Box::new(ApngSink::new(&path, 8, 4, 2, 1.0 / 30.0).unwrap());
+
+    sink.write_frame(0, &frame()).unwrap();
+    sink.write_frame(1, &frame()).unwrap();
+    sink.finish().unwrap();
+
+    let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+    let reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().animation_control().map(|a| a.num_frames), Some(2));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn stopped_apng_is_removed() {
+    let path = temp_path("apng-stopped");
+    let mut sink = Box::new(ApngSink::new(&path, 8, 4, 3, 1.0 / 30.0).unwrap());
+
+    sink.write_frame(0, &frame()).unwrap();
+    drop(sink);
+
+    assert!(!path.exists());
+}
@@ -0,0 +1,37 @@
+use simul8::sim::{CollisionBroadphase, Particle, SimulationState, constraints::CircleConstraint};
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Balls packed into a ring, falling and settling into a pile that keeps pushing particles into new contacts.
+fn settling_scene(broadphase: CollisionBroadphase) -> SimulationState {
+    let mut state = SimulationState::new();
+    state.gravity_accel = glam::vec2(0.0, -9.81);
+    state.particle_collisions = true;
+    state.collision_broadphase = broadphase;
+    state.add_constraint(CircleConstraint::new(1.0, 0.8));
+
+    for i in 0..400 {
+        let (row, column) = (i / 20, i % 20);
+        let position = glam::vec2(-0.6 + column as f32 * 0.06, -0.6 + row as f32 * 0.06);
+        let velocity = glam::vec2(((i * 7) % 11) as f32 * 0.05 - 0.25, ((i * 3) % 5) as f32 * 0.05);
+        state.add_particle(Particle::new(position, 0.025, egui::Color32::WHITE).with_velocity(velocity));
+    }
+
+    state
+}
+
+#[test]
+fn spatial_hash_matches_brute_force() {
+    let mut brute_force = settling_scene(CollisionBroadphase::BruteForce);
+    let mut spatial_hash = settling_scene(CollisionBroadphase::SpatialHash);
+
+    for frame in 1..=300 {
+        brute_force.multi_step(2, DT);
+        spatial_hash.multi_step(2, DT);
+
+        for (index, (a, b)) in brute_force.particles.iter().zip(&spatial_hash.particles).enumerate() {
+            assert_eq!(a.position, b.position, "particle {} differs at frame {}", index, frame);
+        }
+        assert_eq!(brute_force.contacts, spatial_hash.contacts, "contacts differ at frame {}", frame);
+    }
+}
@@ -0,0 +1,54 @@
+use simul8::sim::{Contact, FRAME_RATE, Particle, SimulationState, constraints::{CircleConstraint, HoleCircleConstraint}};
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Tangential speed of a ball along the ring before and after it bounces off the inside of a ring spinning at
+/// `angular_velocity`. The ball starts moving outward along +x and slightly upward, and hits the ring near (1, 0), far from the gap.
+fn tangential_speeds(angular_velocity: f32) -> (f32, f32) {
+    let mut state = SimulationState::new();
+    state.add_constraint(HoleCircleConstraint::new(1.0, 3.0, 3.2, 1.0).with_rotation(angular_velocity));
+    state.add_particle(Particle::new(glam::vec2(0.8, 0.0), 0.02, egui::Color32::WHITE).with_velocity(glam::vec2(1.0, 0.3)));
+
+    for _ in 0..60 {
+        state.single_step(DT);
+        let ball = &state.particles[0];
+        if ball.velocity().x < 0.0 {
+            let tangent = ball.position.normalize().perp();
+            return (glam::vec2(1.0, 0.3).dot(tangent), ball.velocity().dot(tangent));
+        }
+    }
+
+    panic!("the ball never hit the ring");
+}
+
+#[test]
+fn spinning_ring_drags_balls_along() {
+    // The surface near (1, 0) moves at about 2 units per second, well above the ball's tangential speed
+    let (before, after) = tangential_speeds(2.0);
+    assert!(after > before + 0.05, "tangential speed went from {} to {} instead of toward the surface speed", before, after);
+    assert!(after < 2.0, "tangential speed {} overshot the surface speed", after);
+}
+
+#[test]
+fn still_ring_keeps_tangential_speed() {
+    let (before, after) = tangential_speeds(0.0);
+    assert!((after - before).abs() < 1e-3, "tangential speed went from {} to {}", before, after);
+}
+
+#[test]
+fn contacts_keep_constraint_id_after_removal() {
+    let mut state = SimulationState::new();
+    let outer = state.add_constraint(CircleConstraint::new(2.0, 0.8));
+    let inner = state.add_constraint(CircleConstraint::new(0.5, 0.8));
+    let ball = state.add_particle(Particle::new(glam::vec2(0.3, 0.0), 0.05, egui::Color32::WHITE).with_velocity(glam::vec2(3.0, 0.0)));
+
+    let index = state.constraint_index(outer).unwrap();
+    state.constraints.remove(index);
+
+    let hit = (0..30).any(|_| {
+        state = state.next_frame(FRAME_RATE);
+        state.contacts.contains(&Contact::Constraint { constraint: inner, particle: ball })
+    });
+    assert!(hit);
+    assert_eq!(state.constraint_index(inner), Some(0));
+}
@@ -0,0 +1,22 @@
+use simul8::export::{ExportFormat, ExportJob, ExportSettings, ExportStatus};
+use simul8::sim::SimulationState;
+
+#[test]
+fn export_fails_without_frames_from_the_simulation() {
+    let output = std::env::temp_dir().join(format!("simul8-export-test-{}", std::process::id()));
+    let settings = ExportSettings { format: ExportFormat::PngSequence, width: 16, height: 16, output_path: output.clone(), ..Default::default() };
+
+    // The simulation stopped, so its frames never arrive
+    let (snapshot_tx, snapshot_rx) = flume::bounded(1);
+    drop(snapshot_tx);
+
+    let mut job = ExportJob::start(settings, snapshot_rx, SimulationState::new(), 0..=3).unwrap();
+    while job.is_running() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let written = std::fs::read_dir(&output).map(|entries| entries.count()).unwrap_or(0);
+    let _ = std::fs::remove_dir_all(&output);
+    assert!(matches!(job.status(), ExportStatus::Failed(_)), "{:?}", job.status());
+    assert_eq!(written, 0);
+}
@@ -0,0 +1,70 @@
+#![cfg(unix)]
+
+use simul8::export::{ExportFormat, ExportSettings, create_sink};
+
+fn pipe_settings(command: &str) -> ExportSettings {
+    ExportSettings {
+        format: ExportFormat::Pipe,
+        width: 64,
+        height: 32,
+        pipe_command: command.to_string(),
+        ..Default::default()
+    }
+}
+
+fn frame(settings: &ExportSettings) -> egui::ColorImage {
+    egui::ColorImage::new([settings.width as usize, settings.height as usize], egui::Color32::from_rgb(200, 40, 90))
+}
+
+#[test]
+fn pipe_into_command_succeeds() {
+    let settings = pipe_settings("cat > /dev/null");
+    let mut sink = create_sink(&settings, 3).unwrap();
+
+    for index in 0..3 {
+        sink.write_frame(index, &frame(&settings)).unwrap();
+    }
+    sink.finish().unwrap();
+}
+
+#[test]
+fn pipe_reports_failing_command() {
+    let settings = pipe_settings("cat > /dev/null; echo 'encoder broke' >&2; exit 3");
+    let mut sink = create_sink(&settings, 3).unwrap();
+
+    for index in 0..3 {
+        sink.write_frame(index, &frame(&settings)).unwrap();
+    }
+
+    let error = format!("{:#}", sink.finish().unwrap_err());
+    assert!(error.contains("exit status: 3"), "{}", error);
+    assert!(error.contains("encoder broke"), "{}", error);
+}
+
+#[test]
+fn pipe_reports_command_that_stops_reading() {
+    let settings = pipe_settings("exit 1");
+    let mut sink = create_sink(&settings, 60).unwrap();
+
+    // The command may exit before or after the frames fill the pipe, so the error can come from either call
+    let written = (0..60).try_for_each(|index| sink.write_frame(index, &frame(&settings)));
+    assert!(written.is_err() || sink.finish().is_err());
+}
+
+#[test]
+fn pipe_output_path_is_taken_literally() {
+    let dir = std::env::temp_dir().join(format!("simul8-pipe-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("it's $HOME `id` \"quoted\".y4m");
+
+    let mut settings = pipe_settings("cat > {output}");
+    settings.output_path = output.clone();
+
+    let mut sink = create_sink(&settings, 1).unwrap();
+    sink.write_frame(0, &frame(&settings)).unwrap();
+    sink.finish().unwrap();
+
+    let written = std::fs::metadata(&output).map(|m| m.len());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(written.unwrap() > 0);
+}
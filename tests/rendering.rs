@@ -0,0 +1,45 @@
+use egui::Color32;
+use simul8::sim::rendering::{ImageSimRenderer, SimRenderer, Viewport};
+
+/// A 40x20 image showing 4 sim units across, so one sim unit is 10 pixels and the origin is at pixel (20, 10).
+fn renderer() -> ImageSimRenderer {
+    let mut renderer = ImageSimRenderer::new(40, 20, Color32::BLACK);
+    renderer.viewport = Viewport { sim_units_per_vw: 4.0, center: glam::Vec2::ZERO };
+    renderer
+}
+
+#[test]
+fn draws_circle_and_line_at_known_pixels() {
+    let mut renderer = renderer();
+    renderer.circle_filled(glam::Vec2::ZERO, 0.5, Color32::RED);
+    renderer.line_segment(glam::vec2(1.0, 0.0), glam::vec2(1.8, 0.0), 0.2, Color32::GREEN);
+
+    let image = renderer.into_image();
+    let pixel = |x: usize, y: usize| image.pixels[y * image.width() + x];
+
+    // Inside the 5 pixel radius circle
+    for (x, y) in [(20, 10), (19, 9), (17, 12), (22, 7)] {
+        assert_eq!(pixel(x, y), Color32::RED, "pixel ({}, {})", x, y);
+    }
+    // Inside the 2 pixel wide line from x = 30 to 38 along y = 10
+    for (x, y) in [(31, 9), (31, 10), (34, 9), (37, 10)] {
+        assert_eq!(pixel(x, y), Color32::GREEN, "pixel ({}, {})", x, y);
+    }
+    // Clear of both
+    for (x, y) in [(0, 0), (10, 10), (26, 10), (34, 7), (34, 12), (39, 19)] {
+        assert_eq!(pixel(x, y), Color32::BLACK, "pixel ({}, {})", x, y);
+    }
+
+    // The circle's edge is partly covered
+    let edge = pixel(24, 12);
+    assert!(edge.r() > 0 && edge.r() < 255 && edge.g() == 0 && edge.b() == 0, "{:?}", edge);
+}
+
+#[test]
+fn render_clears_to_background() {
+    let mut renderer = renderer();
+    renderer.circle_filled(glam::Vec2::ZERO, 0.5, Color32::RED);
+
+    let image = renderer.render(&simul8::sim::SimulationState::new(), Color32::BLUE);
+    assert!(image.pixels.iter().all(|&p| p == Color32::BLUE));
+}
@@ -0,0 +1,102 @@
+use simul8::export::{ExportFormat, ExportSettings};
+use simul8::sim::{Particle, SimulationState, constraints::*, event::*, links::*, scene};
+
+/// A scene using every constraint, link, trigger and event type.
+fn full_scene() -> SimulationState {
+    let mut s = SimulationState::new();
+    s.gravity_accel = glam::vec2(0.0, -2.0);
+    s.particle_collisions = true;
+    s.tracks.get_mut("gravity_y").set_key(1.0, -4.0);
+
+    let a = s.add_particle(Particle::new(glam::vec2(0.1, 0.2), 0.05, egui::Color32::RED).with_velocity(glam::vec2(0.3, 0.0)));
+    let b = s.add_particle(Particle::new(glam::vec2(-0.2, 0.1), 0.04, egui::Color32::BLUE));
+
+    s.add_constraint(CircleConstraint::new(1.0, 0.9));
+    let hole = s.add_constraint(HoleCircleConstraint::new(0.8, 0.2, 0.6, 1.0).with_rotation(1.5));
+
+    s.add_link(RodLink::new(a, b, 0.3));
+    s.add_link(RopeLink::new(a, b, 0.4));
+    s.add_link(SpringLink::new(a, b, 0.35, 20.0, 0.5));
+
+    let spawn = Particle::new(glam::vec2(0.0, 0.5), 0.03, egui::Color32::GREEN);
+    s.add_trigger_manager(TriggerManager::new(
+        Box::new(AnyLeftCircleTrigger::new(1.2)),
+        vec![Box::new(SpawnEvent::new(spawn)), Box::new(RemoveEvent::new(ParticleTarget::Causes))]
+    ));
+    s.add_trigger_manager(TriggerManager::new(
+        Box::new(AtTimeTrigger::new(0.5)),
+        vec![
+            Box::new(ColorEvent::new(ParticleTarget::All, vec![egui::Color32::YELLOW, egui::Color32::WHITE], true)),
+            Box::new(ScaleRadiusEvent::new(ParticleTarget::Particle(a), 1.1))
+        ]
+    ));
+    s.add_trigger_manager(TriggerManager::new(
+        Box::new(PeriodicTrigger::new(0.0, 0.25)),
+        vec![Box::new(VelocityEvent::new(ParticleTarget::Causes, VelocityChange::Impulse, glam::vec2(0.0, 1.0)))]
+    ));
+    s.add_trigger_manager(TriggerManager::new(
+        Box::new(TimeWindowTrigger::new(1.0, 2.0)),
+        vec![Box::new(SetGravityEvent::new(glam::vec2(0.0, 1.0))), Box::new(ParticleCollisionsEvent::new(Switch::Toggle))]
+    ));
+    s.add_trigger_manager(TriggerManager::new(
+        Box::new(ConstraintContactTrigger::new(Some(hole), Some(a))),
+        vec![Box::new(RemoveConstraintEvent::new(hole))]
+    ));
+    s.add_trigger_manager(TriggerManager::new(
+        Box::new(AllTrigger::new(vec![
+            Box::new(ParticleContactTrigger::new(Some(a), None)),
+            Box::new(NotTrigger::new(Box::new(AtTimeTrigger::new(3.0))))
+        ])),
+        vec![Box::new(AddConstraintEvent::new(Box::new(CircleConstraint::new(0.5, 1.0))))]
+    ));
+    s.add_trigger_manager(TriggerManager::new(Box::new(AnyTrigger::new(vec![Box::new(AtTimeTrigger::new(2.0))])), vec![]));
+
+    s.trigger_managers[0].once = true;
+    s.trigger_managers[2].max_fires = Some(3);
+    s.trigger_managers[3].cooldown = 0.5;
+    s
+}
+
+#[test]
+fn round_trip_is_identical() {
+    let export = ExportSettings { format: ExportFormat::Gif, width: 320, height: 240, ..Default::default() };
+    let saved = scene::save_scene(&full_scene(), Some(&export)).unwrap();
+
+    let loaded = scene::load_scene(&saved).unwrap();
+    assert!(loaded.export.is_some());
+    assert_eq!(scene::save_scene(&loaded.state, loaded.export.as_ref()).unwrap(), saved);
+}
+
+/// `tests/fixtures/scene_v1.json` holds a scene saved by version 1, which later versions have to keep loading.
+#[test]
+fn version_1_scene_is_migrated() {
+    let loaded = scene::load_scene(include_str!("fixtures/scene_v1.json")).unwrap();
+    assert!(loaded.export.is_none());
+
+    let state = loaded.state;
+
+    assert_eq!(state.particles.len(), 2);
+    assert_eq!(state.links.len(), 3);
+    assert_eq!(state.trigger_managers.len(), 1);
+
+    let ids: Vec<u64> = state.constraints.iter().map(|c| c.id()).collect();
+    assert_eq!(ids, [0, 1]);
+    assert_eq!(state.trigger_managers[0].id(), 0);
+}
+
+#[test]
+fn migrated_scene_keeps_numbering_new_objects() {
+    let mut state = scene::load_scene(include_str!("fixtures/scene_v1.json")).unwrap().state;
+
+    assert_eq!(state.add_constraint(CircleConstraint::default()), 2);
+    state.add_trigger_manager(TriggerManager::new(Box::new(AtTimeTrigger::new(1.0)), vec![]));
+    assert_eq!(state.trigger_managers.last().unwrap().id(), 1);
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let data = format!("{{\"version\": {}, \"scene\": {{}}}}", scene::CURRENT_VERSION + 1);
+
+    let error = scene::load_scene(&data).err().unwrap();
+    assert!(format!("{:#}", error).contains("Unsupported scene file version"));
+}
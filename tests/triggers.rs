@@ -0,0 +1,27 @@
+use simul8::sim::{FRAME_RATE, Particle, SimulationState, event::*};
+
+/// Number of particles spawned by `trigger` during the first two seconds, one every time it fires.
+fn times_fired(trigger: Box<dyn SimTrigger>, substeps: u32) -> usize {
+    let mut state = SimulationState::new();
+    state.substeps = substeps;
+    state.add_trigger_manager(TriggerManager::new(
+        trigger,
+        vec![Box::new(SpawnEvent::new(Particle::new(glam::Vec2::ZERO, 0.05, egui::Color32::WHITE)))]
+    ));
+
+    for _ in 0..120 {
+        state = state.next_frame(FRAME_RATE);
+    }
+    state.particles.len()
+}
+
+#[test]
+fn time_window_fires_once_per_frame() {
+    let fired = times_fired(Box::new(TimeWindowTrigger::new(0.5, 1.0)), 1);
+    assert!(fired.abs_diff(31) <= 1, "{}", fired);
+
+    for substeps in [2, 5, 8] {
+        let with_substeps = times_fired(Box::new(TimeWindowTrigger::new(0.5, 1.0)), substeps);
+        assert!(with_substeps.abs_diff(fired) <= 1, "{} substeps fired {} times, 1 substep {}", substeps, with_substeps, fired);
+    }
+}